@@ -1,7 +1,11 @@
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 // Import the Universe from lib.rs:
+use wasm_game_of_life::hashlife::HashLife;
+use wasm_game_of_life::packed::PackedUniverse;
+use wasm_game_of_life::sparse::SparseUniverse;
 use wasm_game_of_life::universe::{Universe, UniverseOption};
 
+#[allow(dead_code)]
 fn fibonacci(n: u64) -> u64 {
     match n {
         0 => 1,
@@ -10,6 +14,13 @@ fn fibonacci(n: u64) -> u64 {
     }
 }
 
+/// The sizes of the (square) universes we benchmark a single tick on.
+const SIZES: [u32; 3] = [64, 256, 1024];
+
+/// The seed of the random universes, so that every run
+/// benchmarks the same cells.
+const SEED: u64 = 2022;
+
 /// Benchmarks the `tick` method of the `Universe` struct.
 ///
 /// It creates a new universe with the `TwoSeven` option and
@@ -23,9 +34,189 @@ fn universe_ticks() {
     }
 }
 
+/// Benchmarks the `tick` method of the `PackedUniverse` struct.
+///
+/// It runs the same 100 ticks as `universe_ticks` on the
+/// bit-packed storage.
+fn packed_universe_ticks() {
+    let universe = Universe::new(UniverseOption::TwoSeven, 64, 64);
+    let mut packed = PackedUniverse::from_universe(&universe).unwrap();
+
+    // Tick the universe 100 times:
+    for _ in 0..100 {
+        packed.tick();
+    }
+}
+
+/// Benchmarks the `HashLife` engine on the R-pentomino.
+///
+/// The R-pentomino stabilizes after 1103 generations, but its
+/// gliders keep flying, so we jump 2^16 generations ahead.
+fn hashlife_r_pentomino() {
+    let mut hashlife = HashLife::new();
+    for (x, y) in [(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)] {
+        hashlife.set_cell(x, y, true);
+    }
+    hashlife.step_pow2(16).unwrap();
+}
+
+/// Benchmarks the `SparseUniverse` on the R-pentomino.
+///
+/// The R-pentomino keeps growing for 1103 generations, so
+/// the universe has to allocate new tiles along the way.
+fn sparse_r_pentomino() {
+    let mut sparse = SparseUniverse::new();
+    sparse.insert_rle(0, 0, "b2o$2o$bo!").unwrap();
+    sparse.tick_n(1000);
+}
+
+/// Benchmarks the `tick` method with Bosco's Rule, a Larger than Life
+/// rule with a range of 5.
+///
+/// Each cell has 120 neighbours, which are counted with a
+/// summed-area table rather than one by one.
+fn bosco_ticks() {
+    let mut universe = Universe::new(UniverseOption::TwoSeven, 64, 64);
+    universe.set_rule("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+
+    // Tick the universe 10 times:
+    for _ in 0..10 {
+        universe.tick();
+    }
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
-    c.bench_function("tick 100", |b| b.iter(|| universe_ticks()));
+    c.bench_function("tick 100", |b| b.iter(universe_ticks));
+    c.bench_function("packed tick 100", |b| b.iter(packed_universe_ticks));
+    c.bench_function("hashlife r-pentomino 2^16", |b| {
+        b.iter(hashlife_r_pentomino)
+    });
+    c.bench_function("bosco tick 10", |b| b.iter(bosco_ticks));
+    c.bench_function("sparse r-pentomino 1000", |b| b.iter(sparse_r_pentomino));
+}
+
+/// The way `Universe::tick` worked before it was double-buffered,
+/// as a reference to compare the `Universe` against.
+///
+/// Every tick clones all cells into a new vector, writes the next
+/// generation into it and drops the old one. The cells are stored as
+/// one byte each, 1 for living and 0 for dead ones, just like a `Cell`.
+struct CloneUniverse {
+    width: u32,
+    height: u32,
+    cells: Vec<u8>,
+}
+
+impl CloneUniverse {
+    /// Copies the cells of a `Universe`, which has to run
+    /// Conway's Game of Life on a torus.
+    fn from_universe(universe: &Universe) -> CloneUniverse {
+        CloneUniverse {
+            width: universe.width(),
+            height: universe.height(),
+            cells: universe
+                .get_cells()
+                .iter()
+                .map(|&cell| cell as u8)
+                .collect(),
+        }
+    }
+
+    /// Returns the index of the cell at `row` and `column`.
+    fn get_index(&self, row: u32, column: u32) -> usize {
+        (row * self.width + column) as usize
+    }
+
+    /// Counts the living neighbours of a cell, wrapping around the
+    /// edges with a branch for each direction, just like
+    /// `Universe::live_neighbor_count` did.
+    fn live_neighbor_count(&self, row: u32, column: u32) -> u8 {
+        let north = if row == 0 { self.height - 1 } else { row - 1 };
+        let south = if row == self.height - 1 { 0 } else { row + 1 };
+        let west = if column == 0 {
+            self.width - 1
+        } else {
+            column - 1
+        };
+        let east = if column == self.width - 1 {
+            0
+        } else {
+            column + 1
+        };
+
+        let mut count = 0;
+        for (neighbor_row, neighbor_column) in [
+            (north, west),
+            (north, column),
+            (north, east),
+            (row, west),
+            (row, east),
+            (south, west),
+            (south, column),
+            (south, east),
+        ] {
+            count += self.cells[self.get_index(neighbor_row, neighbor_column)];
+        }
+        count
+    }
+
+    /// Advances the time by one tick, allocating a new
+    /// vector of cells for the next generation.
+    fn tick(&mut self) {
+        let mut next = self.cells.clone();
+        for row in 0..self.height {
+            for column in 0..self.width {
+                let idx = self.get_index(row, column);
+                let live_neighbors = self.live_neighbor_count(row, column);
+                next[idx] = match (self.cells[idx], live_neighbors) {
+                    // Living cells die of under- and overpopulation:
+                    (1, x) if x < 2 => 0,
+                    (1, 2) | (1, 3) => 1,
+                    (1, x) if x > 3 => 0,
+                    // And dead ones are born with three neighbours:
+                    (0, 3) => 1,
+                    (otherwise, _) => otherwise,
+                };
+            }
+        }
+        self.cells = next;
+    }
+}
+
+/// Benchmarks a single tick on universes of different sizes.
+///
+/// The universes are created outside of the measured closure, so
+/// only the work done by `tick` itself is measured. As both buffers
+/// are reused between ticks, the time per cell should stay the same
+/// no matter how large the universe is.
+///
+/// The `clone` reference allocates a new generation on every tick, like
+/// the `Universe` used to. The double-buffered `universe` does more
+/// work per cell for its general rules, but should beat it as soon as
+/// the allocations grow with the size of the universe.
+fn tick_sizes_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tick");
+    for size in SIZES {
+        let mut universe = Universe::new_random(SEED, 0.5, size, size);
+        // The reference doesn't keep a history of states either:
+        universe.set_history_capacity(0);
+
+        let mut reference = CloneUniverse::from_universe(&universe);
+        group.bench_with_input(BenchmarkId::new("clone", size), &size, |b, _| {
+            b.iter(|| reference.tick())
+        });
+
+        group.bench_with_input(BenchmarkId::new("universe", size), &size, |b, _| {
+            b.iter(|| universe.tick())
+        });
+
+        let mut packed = PackedUniverse::from_universe(&universe).unwrap();
+        group.bench_with_input(BenchmarkId::new("packed", size), &size, |b, _| {
+            b.iter(|| packed.tick())
+        });
+    }
+    group.finish();
 }
 
-criterion_group!(benches, criterion_benchmark);
+criterion_group!(benches, criterion_benchmark, tick_sizes_benchmark);
 criterion_main!(benches);
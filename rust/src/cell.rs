@@ -3,7 +3,7 @@ use wasm_bindgen::prelude::*;
 #[wasm_bindgen]
 // This allows for each `Cell` to be represented as a single byte:
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 /// A `Cell` is one single square in our `Universe`.
 ///
 /// It either is `Dead` (0) or `Alive` (1).
@@ -0,0 +1,400 @@
+//! An object census of a settled `Universe`.
+//!
+//! The census splits the living cells into islands, finds out how each
+//! island behaves on its own and names it with an apgcode, the way
+//! apgsearch and Catagolue identify objects. A block is `xs4_33`, a
+//! blinker `xp2_7` and a glider `xq4_153`, for example.
+//!
+//! Under rules with dying states, the dying cells are part of the
+//! objects, as they keep cells from being born next to them.
+use wasm_bindgen::prelude::*;
+// We need the std::fmt tools to print the census:
+use std::fmt;
+// The census is kept sorted by apgcode, so that it reads the same
+// every time:
+use std::collections::BTreeMap;
+
+use super::cell::Cell;
+use super::pattern::Pattern;
+use super::rule::Rule;
+use super::topology::Topology;
+use super::universe::{Universe, UniverseOption};
+
+/// The longest period of an oscillator or spaceship we look for. Islands
+/// that don't repeat within this many generations are pathological.
+pub const MAX_PERIOD: u32 = 60;
+
+/// The apgcode of islands that don't repeat within `MAX_PERIOD`
+/// generations, or die out on their own.
+pub const PATHOLOGICAL: &str = "PATHOLOGICAL";
+
+/// Cells that are at most this many cells apart in both directions
+/// belong to the same island.
+///
+/// Cells two apart can give birth to a cell between them, so they have
+/// to be run together. This also merges objects that would be stable
+/// on their own, like the two blocks of a bi-block, into a single
+/// pseudo-object with an apgcode of its own.
+const ISLAND_DISTANCE: i64 = 2;
+
+/// The digits of the extended Wechsler format, one for
+/// each combination of the 5 cells of a column.
+const WECHSLER_DIGITS: &[u8; 32] = b"0123456789abcdefghijklmnopqrstuv";
+
+/// The digits that encode the length of a run of
+/// 4 to 39 empty columns after a `y`.
+const RUN_DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// A cell of an island as `(row, column, state)`. The state is 1 for
+/// living cells and 2 upwards for the dying stages, like in
+/// `rle::write_states`.
+type StateCell = (i64, i64, u8);
+
+/// A phase of an object: its cells, moved to the top
+/// left corner, and whether the object moves.
+type Phase = (Vec<StateCell>, bool);
+
+/// One of the eight ways to rotate and mirror a cell.
+type Orientation = fn(i64, i64) -> (i64, i64);
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// The result of a census: how often each object was found,
+/// by its apgcode.
+pub struct Census {
+    objects: BTreeMap<String, u32>,
+}
+
+impl Census {
+    /// Returns the number of objects per apgcode.
+    pub fn objects(&self) -> &BTreeMap<String, u32> {
+        &self.objects
+    }
+}
+
+#[wasm_bindgen]
+impl Census {
+    /// Returns how often the object with the given `apgcode` was found.
+    pub fn count(&self, apgcode: &str) -> u32 {
+        self.objects.get(apgcode).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of objects that were found.
+    pub fn total(&self) -> u32 {
+        self.objects.values().sum()
+    }
+
+    /// Returns the census as a `String` with one
+    /// `apgcode count` line per object.
+    pub fn render(&self) -> String {
+        self.to_string()
+    }
+}
+
+// The most common objects come first, just like on Catagolue.
+impl fmt::Display for Census {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut objects: Vec<(&String, &u32)> = self.objects.iter().collect();
+        objects.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (apgcode, count) in objects {
+            writeln!(f, "{} {}", apgcode, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Takes a census of all living and dying cells of the `Universe`,
+/// which runs the `Rule`.
+///
+/// The `Universe` should have settled before, otherwise islands that
+/// are about to interact are counted as separate objects.
+pub fn take(universe: &Universe, rule: &Rule) -> Census {
+    let mut census = Census::default();
+    for island in islands(universe) {
+        let apgcode = classify_states(&island, rule);
+        *census.objects.entry(apgcode).or_insert(0) += 1;
+    }
+    census
+}
+
+/// Splits the living and dying cells of the `Universe` into islands.
+///
+/// Each island is a list of cells with their state. The positions of
+/// an island that crosses an edge of the `Universe` continue beyond it,
+/// so that the island stays in one piece.
+///
+/// # Algorithm explanation
+///
+/// We start a flood fill at every living or dying cell that isn't part
+/// of an island yet. The fill moves on to all such cells within
+/// `ISLAND_DISTANCE`, which are resolved according to the `Topology`.
+/// Next to the index of each cell, we keep the unresolved position it
+/// was reached at, which continues across the edges.
+fn islands(universe: &Universe) -> Vec<Vec<StateCell>> {
+    let cells = universe.get_cells();
+    let dying = universe.get_dying_cells();
+    // The living state is 1 and the dying stages follow it:
+    let state = |idx: usize| match cells[idx] {
+        Cell::Alive => 1,
+        Cell::Dead => dying.map_or(0, |dying| match dying[idx] {
+            0 => 0,
+            stage => stage + 1,
+        }),
+    };
+    let mut visited = vec![false; cells.len()];
+    let mut islands = Vec::new();
+
+    for start in 0..cells.len() {
+        if visited[start] || state(start) == 0 {
+            continue;
+        }
+        visited[start] = true;
+
+        let width = universe.width() as usize;
+        let mut island = Vec::new();
+        let mut stack = vec![((start / width) as i64, (start % width) as i64, start)];
+        while let Some((row, column, idx)) = stack.pop() {
+            island.push((row, column, state(idx)));
+            for delta_row in -ISLAND_DISTANCE..=ISLAND_DISTANCE {
+                for delta_column in -ISLAND_DISTANCE..=ISLAND_DISTANCE {
+                    let (neighbor_row, neighbor_column) = (row + delta_row, column + delta_column);
+                    if let Some(idx) = universe.resolve(neighbor_row, neighbor_column) {
+                        if !visited[idx] && state(idx) > 0 {
+                            visited[idx] = true;
+                            stack.push((neighbor_row, neighbor_column, idx));
+                        }
+                    }
+                }
+            }
+        }
+        islands.push(island);
+    }
+
+    islands
+}
+
+/// Returns the apgcode of an island of living cells under the `Rule`.
+///
+/// The island is run on its own until it returns to its first phase,
+/// which tells us its period and whether it moves. Still lifes get a
+/// code starting with `xs` and their population, oscillators `xp` and
+/// spaceships `xq`, each followed by their period. The rest of the
+/// code is the canonical extended Wechsler format of the island.
+pub fn classify(cells: &[(i64, i64)], rule: &Rule) -> String {
+    let cells: Vec<StateCell> = cells
+        .iter()
+        .map(|&(row, column)| (row, column, 1))
+        .collect();
+    classify_states(&cells, rule)
+}
+
+/// Returns the apgcode of an island of living and dying cells, just
+/// like `classify`.
+///
+/// The period only ends once the dying stages repeat as well, but
+/// the population and the extended Wechsler format only count the
+/// living cells.
+fn classify_states(cells: &[StateCell], rule: &Rule) -> String {
+    let phases = match phases(cells, rule) {
+        Some(phases) => phases,
+        None => return PATHOLOGICAL.to_string(),
+    };
+
+    let (first, moved) = &phases[0];
+    let prefix = if phases.len() == 1 && !moved {
+        let population = first.iter().filter(|&&(_, _, state)| state == 1).count();
+        format!("xs{}", population)
+    } else if *moved {
+        format!("xq{}", phases.len())
+    } else {
+        format!("xp{}", phases.len())
+    };
+
+    format!("{}_{}", prefix, canonical_wechsler(&phases))
+}
+
+/// Runs the cells on an otherwise empty plane until they return to
+/// their first phase, shifted or not.
+///
+/// Returns all phases, each normalized to the top left corner of its
+/// bounding box and together with whether the cells moved over the
+/// whole period. Cells that die out or don't repeat within `MAX_PERIOD`
+/// generations return `None`.
+fn phases(cells: &[StateCell], rule: &Rule) -> Option<Vec<Phase>> {
+    let (first, _, _) = normalize(cells);
+    let height = first.iter().map(|&(row, _, _)| row + 1).max()?;
+    let width = first.iter().map(|&(_, column, _)| column + 1).max()?;
+
+    // Nothing moves faster than one cell per generation, so a margin of
+    // `MAX_PERIOD` cells keeps the cells away from the dead border:
+    let margin = MAX_PERIOD as i64 + 2;
+    let mut universe = Universe::new(
+        UniverseOption::Dead,
+        (width + 2 * margin) as u32,
+        (height + 2 * margin) as u32,
+    );
+    universe.set_topology(Topology::Plane).ok()?;
+    // The `Pattern` brings the rule along:
+    let mut pattern = Pattern::new(width as u32, height as u32);
+    let mut states = vec![0; (width * height) as usize];
+    for &(row, column, state) in first.iter() {
+        if state == 1 {
+            pattern.set(row as u32, column as u32, Cell::Alive);
+        }
+        states[(row * width + column) as usize] = state;
+    }
+    pattern.set_rule(Some(rule.clone()));
+    universe
+        .insert_states(&pattern, Some(&states), margin as u32, margin as u32)
+        .ok()?;
+
+    let universe_width = universe.width() as i64;
+    let mut phases = vec![first.clone()];
+    for _ in 0..MAX_PERIOD {
+        universe.tick();
+        let dying = universe.get_dying_cells();
+        let cells: Vec<StateCell> = universe
+            .get_cells()
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, &cell)| {
+                let state = match (cell, dying.map_or(0, |dying| dying[idx])) {
+                    (Cell::Alive, _) => 1,
+                    (Cell::Dead, 0) => return None,
+                    (Cell::Dead, stage) => stage + 1,
+                };
+                let (row, column) = (idx as i64 / universe_width, idx as i64 % universe_width);
+                Some((row, column, state))
+            })
+            .collect();
+        if cells.is_empty() {
+            return None;
+        }
+
+        let (phase, phase_top, phase_left) = normalize(&cells);
+        if phase == first {
+            let moved = (phase_top, phase_left) != (margin, margin);
+            return Some(phases.into_iter().map(|phase| (phase, moved)).collect());
+        }
+        phases.push(phase);
+    }
+
+    None
+}
+
+/// Moves the cells to the top left corner and sorts them.
+///
+/// Returns the moved cells and the row and column of
+/// their original top left corner.
+fn normalize(cells: &[StateCell]) -> (Vec<StateCell>, i64, i64) {
+    let top = cells.iter().map(|&(row, _, _)| row).min().unwrap_or(0);
+    let left = cells
+        .iter()
+        .map(|&(_, column, _)| column)
+        .min()
+        .unwrap_or(0);
+    let mut normalized: Vec<StateCell> = cells
+        .iter()
+        .map(|&(row, column, state)| (row - top, column - left, state))
+        .collect();
+    normalized.sort_unstable();
+    (normalized, top, left)
+}
+
+/// Returns the extended Wechsler format of the living cells of all
+/// phases in all eight orientations that comes first, i.e. the shortest
+/// one and the alphabetically first among the shortest.
+fn canonical_wechsler(phases: &[Phase]) -> String {
+    let orientations: [Orientation; 8] = [
+        |row, column| (row, column),
+        |row, column| (row, -column),
+        |row, column| (-row, column),
+        |row, column| (-row, -column),
+        |row, column| (column, row),
+        |row, column| (column, -row),
+        |row, column| (-column, row),
+        |row, column| (-column, -row),
+    ];
+
+    // Phases without living cells would have an empty code:
+    phases
+        .iter()
+        .filter(|(cells, _)| cells.iter().any(|&(_, _, state)| state == 1))
+        .flat_map(|(cells, _)| {
+            orientations.iter().map(move |orientation| {
+                let oriented: Vec<StateCell> = cells
+                    .iter()
+                    .filter(|&&(_, _, state)| state == 1)
+                    .map(|&(row, column, state)| {
+                        let (row, column) = orientation(row, column);
+                        (row, column, state)
+                    })
+                    .collect();
+                wechsler(&normalize(&oriented).0)
+            })
+        })
+        .min_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)))
+        .unwrap_or_default()
+}
+
+/// Returns the cells in the extended Wechsler format.
+///
+/// # Algorithm explanation
+///
+/// The cells are cut into strips of 5 rows, which are separated by a
+/// `z`. Each column of a strip is a single digit: the 5 cells from top
+/// to bottom are the bits of a number from 0 to 31, which is written
+/// in base 32. Runs of empty columns are shortened: `w` stands for 2
+/// of them, `x` for 3 and `y` followed by a digit for 4 to 39. Empty
+/// columns at the end of a strip are left out.
+fn wechsler(cells: &[StateCell]) -> String {
+    let height = cells.iter().map(|&(row, _, _)| row + 1).max().unwrap_or(0);
+    let width = cells
+        .iter()
+        .map(|&(_, column, _)| column + 1)
+        .max()
+        .unwrap_or(0);
+
+    let mut strips = Vec::new();
+    for strip_top in (0..height).step_by(5) {
+        let mut columns = vec![0usize; width as usize];
+        for &(row, column, _) in cells {
+            if (strip_top..strip_top + 5).contains(&row) {
+                columns[column as usize] |= 1 << (row - strip_top);
+            }
+        }
+        // Empty columns at the end of a strip are left out:
+        while columns.last() == Some(&0) {
+            columns.pop();
+        }
+
+        let mut strip = String::new();
+        let mut empty = 0;
+        for column in columns.into_iter().chain(std::iter::once(usize::MAX)) {
+            if column == 0 {
+                empty += 1;
+                continue;
+            }
+            // Write out the run of empty columns before this column:
+            while empty > 0 {
+                let run = empty.min(39);
+                match run {
+                    1 => strip.push('0'),
+                    2 => strip.push('w'),
+                    3 => strip.push('x'),
+                    _ => {
+                        strip.push('y');
+                        strip.push(RUN_DIGITS[run - 4] as char);
+                    }
+                }
+                empty -= run;
+            }
+            if column != usize::MAX {
+                strip.push(WECHSLER_DIGITS[column] as char);
+            }
+        }
+        strips.push(strip);
+    }
+
+    strips.join("z")
+}
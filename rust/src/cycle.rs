@@ -0,0 +1,182 @@
+use wasm_bindgen::prelude::*;
+// We keep the hashes in the order we've seen them and
+// look them up by their value:
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hasher;
+
+/// The number of states a `StateHistory` remembers by default.
+pub const DEFAULT_CAPACITY: u32 = 64;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// An `Oscillation` describes a `Universe` that returned to
+/// a state it has been in before.
+///
+/// The `period` is the number of generations between both occurrences
+/// of the state, a `period` of 1 means that nothing changes anymore.
+/// `first_seen_generation` is the generation in which the state was
+/// seen for the first time.
+pub struct Oscillation {
+    pub period: u32,
+    pub first_seen_generation: u32,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The `Stability` of a `Universe` tells if a run has settled down.
+pub enum Stability {
+    /// No state was repeated within the remembered generations.
+    Evolving,
+    /// There's no living cell left.
+    Extinct,
+    /// The cells don't change anymore.
+    Still,
+    /// The cells repeat with a period larger than 1.
+    Oscillating,
+}
+
+/// The multiplier of `StateHasher`, taken from FxHash.
+const HASH_MULTIPLIER: u64 = 0x517c_c1b7_2722_0a95;
+
+/// A fast `Hasher` for the states of a `Universe`.
+///
+/// The default `Hasher` of the standard library guards hash maps against
+/// attackers who pick the keys, which makes it slow on a million cells.
+/// The states we hash aren't picked to collide, so we mix in one word
+/// at a time with a rotation and a multiplication, like FxHash does.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StateHasher {
+    hash: u64,
+}
+
+impl StateHasher {
+    /// Mixes a single word into the hash.
+    #[inline]
+    fn add(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(HASH_MULTIPLIER);
+    }
+}
+
+impl Hasher for StateHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        // Eight bytes at a time, and the rest one by one:
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            let mut word = [0; 8];
+            word.copy_from_slice(chunk);
+            self.add(u64::from_le_bytes(word));
+        }
+        for &byte in chunks.remainder() {
+            self.add(byte as u64);
+        }
+    }
+
+    fn write_u8(&mut self, value: u8) {
+        self.add(value as u64);
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.add(value);
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.add(value as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// A `StateHistory` remembers the hashes of the last states of a
+/// `Universe` to detect when it repeats itself.
+///
+/// Only the hashes are stored, so remembering a state costs the same
+/// no matter how large the `Universe` is. Two different states with the
+/// same hash would be reported as a repetition, but with 64 bit hashes
+/// that's not something we expect to ever see.
+#[derive(Clone, Debug)]
+pub struct StateHistory {
+    capacity: u32,
+    // The hashes in the order they were recorded,
+    // together with their generation:
+    hashes: VecDeque<(u64, u32)>,
+    // The generation in which we've seen each hash the last time:
+    generations: HashMap<u64, u32>,
+    oscillation: Option<Oscillation>,
+}
+
+impl StateHistory {
+    /// Creates an empty `StateHistory` that remembers
+    /// up to `capacity` states.
+    pub fn new(capacity: u32) -> StateHistory {
+        StateHistory {
+            capacity,
+            hashes: VecDeque::new(),
+            generations: HashMap::new(),
+            oscillation: None,
+        }
+    }
+
+    /// Records the `hash` of the state in `generation`.
+    ///
+    /// If we've seen the hash before, the first `Oscillation` we
+    /// detect is kept until the history is cleared.
+    pub fn record(&mut self, hash: u64, generation: u32) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if let Some(&seen) = self.generations.get(&hash) {
+            self.oscillation.get_or_insert(Oscillation {
+                period: generation - seen,
+                first_seen_generation: seen,
+            });
+        }
+
+        self.hashes.push_back((hash, generation));
+        self.generations.insert(hash, generation);
+
+        // Forget the oldest state once we're over capacity. Its hash
+        // could have been seen again since, so we only remove it from
+        // the lookup if it still points to the forgotten generation:
+        while self.hashes.len() > self.capacity as usize {
+            let (old_hash, old_generation) = self.hashes.pop_front().unwrap();
+            if self.generations.get(&old_hash) == Some(&old_generation) {
+                self.generations.remove(&old_hash);
+            }
+        }
+    }
+
+    /// Forgets all recorded states and the detected `Oscillation`.
+    pub fn clear(&mut self) {
+        self.hashes.clear();
+        self.generations.clear();
+        self.oscillation = None;
+    }
+
+    /// Returns `true` if the history remembers any states at all,
+    /// otherwise there's no need to hash them.
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Returns the number of states the history remembers.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Changes the number of states the history remembers.
+    ///
+    /// As this changes which repetitions can be detected,
+    /// the history is cleared.
+    pub fn set_capacity(&mut self, capacity: u32) {
+        self.capacity = capacity;
+        self.clear();
+    }
+
+    /// Returns the detected `Oscillation`, if there is one.
+    pub fn oscillation(&self) -> Option<Oscillation> {
+        self.oscillation
+    }
+}
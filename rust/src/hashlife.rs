@@ -0,0 +1,550 @@
+use wasm_bindgen::prelude::*;
+// HashLife lives off looking up nodes and results it has seen before:
+use std::collections::HashMap;
+// We need the std::fmt tools to print errors:
+use std::fmt;
+
+use super::cell::Cell;
+use super::rule::{Rule, RuleError};
+use super::universe::{Universe, UniverseOption};
+
+/// The index of a `Node` in the arena of a `HashLife` universe.
+type NodeId = u32;
+
+/// The leaves of the quadtree are single cells.
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+/// The number of nodes after which we throw away all nodes that aren't
+/// part of the current pattern anymore, together with the memoized results.
+const GARBAGE_COLLECTION_THRESHOLD: usize = 1 << 22;
+
+/// The highest level of the root. Coordinates are `i64` relative to the
+/// center of the root, so it can't be more than 2^63 cells wide.
+const MAX_LEVEL: u8 = 63;
+
+/// The errors that can occur while advancing a `HashLife` universe.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HashLifeError {
+    /// Advancing by 2^`k` generations at once would need a root
+    /// beyond the `MAX_LEVEL`.
+    StepTooLarge { k: u8 },
+    /// The generation counter would overflow.
+    GenerationOverflow,
+}
+
+impl fmt::Display for HashLifeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HashLifeError::StepTooLarge { k } => write!(
+                f,
+                "the universe can't be advanced by 2^{} generations at once",
+                k
+            ),
+            HashLifeError::GenerationOverflow => {
+                write!(f, "the generation counter of the universe would overflow")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HashLifeError {}
+
+// Errors are thrown as exceptions on the JavaScript side:
+impl From<HashLifeError> for JsValue {
+    fn from(error: HashLifeError) -> JsValue {
+        JsValue::from_str(&error.to_string())
+    }
+}
+
+/// A `Node` of the quadtree.
+///
+/// A node of `level` n is a square of 2^n x 2^n cells, made up of its
+/// four quadrants of level n - 1 in the order north-west, north-east,
+/// south-west and south-east. Nodes of level 0 are single cells.
+#[derive(Clone, Copy)]
+struct Node {
+    level: u8,
+    children: [NodeId; 4],
+    population: u64,
+}
+
+#[wasm_bindgen]
+/// A `HashLife` universe is an unbounded plane of cells that can be
+/// advanced by huge numbers of generations at once.
+///
+/// The plane is stored as a quadtree, in which equal squares of cells
+/// are represented by the very same `Node`. For each `Node` we memoize
+/// the center of the square some generations into the future, so that
+/// repetitive patterns (like the ones of breeders or guns) are only
+/// ever computed once.
+///
+/// Cells are addressed by their `x` (column) and `y` (row) coordinate,
+/// where the origin is the center of the plane.
+pub struct HashLife {
+    nodes: Vec<Node>,
+    // Maps the quadrants of a node to the one node that holds them:
+    lookup: HashMap<[NodeId; 4], NodeId>,
+    // Maps a node and the power of two of the generations it was
+    // advanced by to the center of the node at that time:
+    results: HashMap<(NodeId, u8), NodeId>,
+    // The node of only dead cells for each level:
+    empty: Vec<NodeId>,
+    root: NodeId,
+    generation: u64,
+    rule: Rule,
+}
+
+// These functions won't be exposed to JavaScript as
+// they're only meant to handle the internal state:
+impl HashLife {
+    /// Returns the `Node` with the given `id`.
+    fn node(&self, id: NodeId) -> Node {
+        self.nodes[id as usize]
+    }
+
+    /// Returns the one `Node` that is made up of the given quadrants.
+    fn join(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(&id) = self.lookup.get(&children) {
+            return id;
+        }
+
+        let level = self.node(children[0]).level + 1;
+        let population = children
+            .iter()
+            .map(|&child| self.node(child).population)
+            .sum();
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(Node {
+            level,
+            children,
+            population,
+        });
+        self.lookup.insert(children, id);
+        id
+    }
+
+    /// Returns the `Node` of only dead cells of the given `level`.
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let child = *self.empty.last().unwrap();
+            let id = self.join([child; 4]);
+            self.empty.push(id);
+        }
+        self.empty[level as usize]
+    }
+
+    /// Returns the center of a `Node`, one level below it.
+    fn center(&mut self, id: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.node(id).children;
+        self.join([
+            self.node(nw).children[3],
+            self.node(ne).children[2],
+            self.node(sw).children[1],
+            self.node(se).children[0],
+        ])
+    }
+
+    /// Returns a `Node` one level above the given one, which holds
+    /// the given `Node` in its center and dead cells around it.
+    fn expand(&mut self, id: NodeId) -> NodeId {
+        let node = self.node(id);
+        let empty = self.empty(node.level - 1);
+        let [nw, ne, sw, se] = node.children;
+        let children = [
+            self.join([empty, empty, empty, nw]),
+            self.join([empty, empty, ne, empty]),
+            self.join([empty, sw, empty, empty]),
+            self.join([se, empty, empty, empty]),
+        ];
+        self.join(children)
+    }
+
+    /// Returns a copy of the `Node` in which the cell at `x` and `y`
+    /// (relative to the top left corner of the node) is set to `cell`.
+    fn set(&mut self, id: NodeId, x: u64, y: u64, cell: Cell) -> NodeId {
+        let node = self.node(id);
+        if node.level == 0 {
+            return match cell {
+                Cell::Alive => ALIVE,
+                Cell::Dead => DEAD,
+            };
+        }
+
+        let half = 1 << (node.level - 1);
+        let quadrant = (y >= half) as usize * 2 + (x >= half) as usize;
+        let mut children = node.children;
+        children[quadrant] = self.set(children[quadrant], x % half, y % half, cell);
+        self.join(children)
+    }
+
+    /// Returns the `Cell` at `x` and `y`, relative to
+    /// the top left corner of the `Node`.
+    fn get(&self, id: NodeId, x: u64, y: u64) -> Cell {
+        let node = self.node(id);
+        if node.population == 0 {
+            return Cell::Dead;
+        }
+        if node.level == 0 {
+            return Cell::Alive;
+        }
+
+        let half = 1 << (node.level - 1);
+        let quadrant = (y >= half) as usize * 2 + (x >= half) as usize;
+        self.get(node.children[quadrant], x % half, y % half)
+    }
+
+    /// Returns the half of the side length of the root node, which is
+    /// the offset between the coordinates and the top left corner.
+    fn root_offset(&self) -> i64 {
+        1 << (self.node(self.root).level - 1)
+    }
+
+    /// Expands the root until the cell at `x` and `y` is part of it.
+    fn grow_to(&mut self, x: i64, y: i64) {
+        loop {
+            let offset = self.root_offset();
+            if (-offset..offset).contains(&x) && (-offset..offset).contains(&y) {
+                return;
+            }
+            self.root = self.expand(self.root);
+        }
+    }
+
+    /// Advances the 4x4 cells of a `Node` of level 2 by one generation
+    /// and returns the 2x2 cells in their center.
+    fn step_leaf(&mut self, id: NodeId) -> NodeId {
+        let mut cells = [[Cell::Dead; 4]; 4];
+        for (y, row) in cells.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                *cell = self.get(id, x as u64, y as u64);
+            }
+        }
+
+        let mut center = [DEAD; 4];
+        for (quadrant, (x, y)) in [(1, 1), (2, 1), (1, 2), (2, 2)].iter().enumerate() {
+            let mut live_neighbors = 0;
+            for delta_y in [0, 1, 2] {
+                for delta_x in [0, 1, 2] {
+                    if (delta_x, delta_y) != (1, 1) {
+                        live_neighbors += cells[y + delta_y - 1][x + delta_x - 1] as u32;
+                    }
+                }
+            }
+            center[quadrant] = match self.rule.next_cell(cells[*y][*x], live_neighbors) {
+                Cell::Alive => ALIVE,
+                Cell::Dead => DEAD,
+            };
+        }
+
+        self.join(center)
+    }
+
+    /// Returns the center of a `Node`, advanced by 2^`k` generations.
+    ///
+    /// # Algorithm explanation
+    ///
+    /// We split the node into nine overlapping sub-squares of half its
+    /// size. Each of them is advanced recursively, which gives us nine
+    /// squares of a quarter of the size. These are combined into four
+    /// overlapping squares of half the size, which are advanced again
+    /// and finally combined into the center of the node.
+    ///
+    /// If we want to advance by the full 2^(level - 2) generations, both
+    /// recursive steps go half of the way. For smaller steps, the first
+    /// step doesn't advance at all and only takes the centers.
+    ///
+    /// # Panics
+    /// The method panics if `k` is larger than the level of the node
+    /// minus 2, as the center can't be known any further in time.
+    fn advance(&mut self, id: NodeId, k: u8) -> NodeId {
+        let node = self.node(id);
+        assert!(k + 2 <= node.level, "a node can't be advanced that far");
+
+        if node.population == 0 {
+            return self.empty(node.level - 1);
+        }
+        if let Some(&result) = self.results.get(&(id, k)) {
+            return result;
+        }
+
+        let result = if node.level == 2 {
+            self.step_leaf(id)
+        } else {
+            let [nw, ne, sw, se] = node.children;
+            let [_, nw_ne, nw_sw, nw_se] = self.node(nw).children;
+            let [ne_nw, _, ne_sw, ne_se] = self.node(ne).children;
+            let [sw_nw, sw_ne, _, sw_se] = self.node(sw).children;
+            let [se_nw, se_ne, se_sw, _] = self.node(se).children;
+
+            // The nine overlapping sub-squares, row by row:
+            let squares = [
+                nw,
+                self.join([nw_ne, ne_nw, nw_se, ne_sw]),
+                ne,
+                self.join([nw_sw, nw_se, sw_nw, sw_ne]),
+                self.join([nw_se, ne_sw, sw_ne, se_nw]),
+                self.join([ne_sw, ne_se, se_nw, se_ne]),
+                sw,
+                self.join([sw_ne, se_nw, sw_se, se_sw]),
+                se,
+            ];
+
+            let full_speed = k + 2 == node.level;
+            let mut advanced = [DEAD; 9];
+            for (square, result) in squares.iter().zip(advanced.iter_mut()) {
+                *result = if full_speed {
+                    self.advance(*square, k - 1)
+                } else {
+                    self.center(*square)
+                };
+            }
+
+            let second_k = if full_speed { k - 1 } else { k };
+            let [a, b, c, d, e, f, g, h, i] = advanced;
+            let quadrants = [
+                self.join([a, b, d, e]),
+                self.join([b, c, e, f]),
+                self.join([d, e, g, h]),
+                self.join([e, f, h, i]),
+            ];
+            let mut children = [DEAD; 4];
+            for (quadrant, child) in quadrants.iter().zip(children.iter_mut()) {
+                *child = self.advance(*quadrant, second_k);
+            }
+            self.join(children)
+        };
+
+        self.results.insert((id, k), result);
+        result
+    }
+
+    /// Throws away all nodes that aren't part of the current root,
+    /// together with all memoized results.
+    fn collect_garbage(&mut self) {
+        let old_nodes = std::mem::take(&mut self.nodes);
+        self.lookup.clear();
+        self.results.clear();
+        self.empty.clear();
+        self.nodes.extend_from_slice(&old_nodes[..2]);
+        self.empty.push(DEAD);
+
+        /// Copies a node and its quadrants over from the old arena.
+        fn copy(
+            hashlife: &mut HashLife,
+            old_nodes: &[Node],
+            copied: &mut HashMap<NodeId, NodeId>,
+            id: NodeId,
+        ) -> NodeId {
+            if id <= ALIVE {
+                return id;
+            }
+            if let Some(&new_id) = copied.get(&id) {
+                return new_id;
+            }
+            let mut children = old_nodes[id as usize].children;
+            for child in children.iter_mut() {
+                *child = copy(hashlife, old_nodes, copied, *child);
+            }
+            let new_id = hashlife.join(children);
+            copied.insert(id, new_id);
+            new_id
+        }
+
+        self.root = copy(self, &old_nodes, &mut HashMap::new(), self.root);
+    }
+}
+
+// These methods for `HashLife` will be exposed to the
+// JavaScript API:
+#[wasm_bindgen]
+impl HashLife {
+    /// Creates an empty `HashLife` universe that evolves
+    /// by Conway's Game of Life.
+    pub fn new() -> HashLife {
+        super::utils::set_panic_hook();
+
+        // The two leaves are the first nodes in the arena,
+        // so that their ids match `DEAD` and `ALIVE`:
+        let leaf = |population| Node {
+            level: 0,
+            children: [DEAD; 4],
+            population,
+        };
+        let mut hashlife = HashLife {
+            nodes: vec![leaf(0), leaf(1)],
+            lookup: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+            root: DEAD,
+            generation: 0,
+            rule: Rule::default(),
+        };
+        // We start off with a root of 8x8 cells:
+        hashlife.root = hashlife.empty(3);
+        hashlife
+    }
+
+    /// Creates a `HashLife` universe with the cells and `Rule` of an
+    /// `Universe`. The top left corner of the `Universe` ends up at
+    /// the origin.
+    ///
+    /// The `Topology` of the `Universe` isn't carried over: the plane
+    /// is unbounded, so cells that would wrap around or vanish at the
+    /// edges of the `Universe` keep going instead. Only patterns that
+    /// stay clear of the edges evolve the same way in both.
+    ///
+    /// # Errors
+    /// Rules with `B0` would fill the whole unbounded plane within a
+    /// single generation and can't be run by HashLife, just like rules
+    /// with another neighbourhood than the 8 cell one or dying states.
+    pub fn from_universe(universe: &Universe) -> Result<HashLife, RuleError> {
+        let mut hashlife = HashLife::new();
+        hashlife.set_rule(&universe.rule())?;
+        for row in 0..universe.height() {
+            for column in 0..universe.width() {
+                if universe.get_cells()[universe.get_index(row, column)] == Cell::Alive {
+                    hashlife.set_cell(column as i64, row as i64, true);
+                }
+            }
+        }
+        Ok(hashlife)
+    }
+
+    /// Returns a region of the plane as an `Universe` with
+    /// the same `Rule`.
+    ///
+    /// The top left corner of the region is at `x` and `y` and it
+    /// spans `width` columns and `height` rows.
+    pub fn to_universe(&self, x: i64, y: i64, width: u32, height: u32) -> Universe {
+        let mut universe = Universe::new(UniverseOption::Dead, width, height);
+        // The rule of a `HashLife` universe is always a valid rulestring:
+        universe.set_rule(&self.rule.to_string()).unwrap();
+
+        let mut living = Vec::new();
+        for row in 0..height {
+            for column in 0..width {
+                if self.is_alive(x + column as i64, y + row as i64) {
+                    living.push((row, column));
+                }
+            }
+        }
+        universe.set_cells(&living);
+        universe
+    }
+
+    /// Sets the `Rule` by which the universe evolves.
+    ///
+    /// The `rule` is a rulestring in the B/S notation. Rules with `B0`,
+    /// another neighbourhood than the 8 cell one or dying states
+    /// are rejected.
+    pub fn set_rule(&mut self, rule: &str) -> Result<(), RuleError> {
+        let rule: Rule = rule.parse()?;
+        if rule.is_birth(0) || !rule.is_life_like() {
+            return Err(RuleError::Unsupported(rule.to_string()));
+        }
+        self.rule = rule;
+        // The memoized results were computed with the previous rule:
+        self.results.clear();
+        Ok(())
+    }
+
+    /// Returns the current `Rule` as a rulestring in the B/S notation.
+    pub fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /// Sets the cell at `x` and `y` to alive or dead.
+    pub fn set_cell(&mut self, x: i64, y: i64, alive: bool) {
+        self.grow_to(x, y);
+        let offset = self.root_offset();
+        let cell = if alive { Cell::Alive } else { Cell::Dead };
+        self.root = self.set(self.root, (x + offset) as u64, (y + offset) as u64, cell);
+    }
+
+    /// Returns `true` if the cell at `x` and `y` is alive.
+    pub fn is_alive(&self, x: i64, y: i64) -> bool {
+        let offset = self.root_offset();
+        (-offset..offset).contains(&x)
+            && (-offset..offset).contains(&y)
+            && self.get(self.root, (x + offset) as u64, (y + offset) as u64) == Cell::Alive
+    }
+
+    /// Advances the universe by 2^`k` generations at once.
+    ///
+    /// The root is expanded until its border is empty and it's large
+    /// enough to be advanced that far. As no pattern can grow faster than
+    /// one cell per generation, expanding it once more guarantees that
+    /// the center of the root holds the whole pattern afterwards.
+    ///
+    /// # Errors
+    /// A root that would have to grow beyond the `MAX_LEVEL` is rejected
+    /// with `HashLifeError::StepTooLarge` and a generation counter that
+    /// would overflow with `HashLifeError::GenerationOverflow`. The cells
+    /// stay as they are in both cases.
+    pub fn step_pow2(&mut self, k: u8) -> Result<(), HashLifeError> {
+        // The root is expanded once more before it's advanced:
+        if k > MAX_LEVEL - 3 {
+            return Err(HashLifeError::StepTooLarge { k });
+        }
+        let generation = self
+            .generation
+            .checked_add(1 << k)
+            .ok_or(HashLifeError::GenerationOverflow)?;
+
+        loop {
+            let level = self.node(self.root).level;
+            if level >= MAX_LEVEL {
+                return Err(HashLifeError::StepTooLarge { k });
+            }
+            let center = self.center(self.root);
+            if level >= k + 2 && self.node(center).population == self.population() {
+                break;
+            }
+            self.root = self.expand(self.root);
+        }
+        self.root = self.expand(self.root);
+        self.root = self.advance(self.root, k);
+        self.generation = generation;
+
+        if self.nodes.len() > GARBAGE_COLLECTION_THRESHOLD {
+            self.collect_garbage();
+        }
+        Ok(())
+    }
+
+    /// Advances the universe by `generations` generations, in
+    /// steps of the powers of two that make up the number.
+    ///
+    /// # Errors
+    /// The same errors as for `step_pow2`. The universe is only
+    /// advanced if the generation counter doesn't overflow, but steps
+    /// that were already taken stay if a later one is too large.
+    pub fn step(&mut self, generations: u64) -> Result<(), HashLifeError> {
+        self.generation
+            .checked_add(generations)
+            .ok_or(HashLifeError::GenerationOverflow)?;
+        for k in 0..u64::BITS as u8 {
+            if generations & (1 << k) != 0 {
+                self.step_pow2(k)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of living cells.
+    pub fn population(&self) -> u64 {
+        self.node(self.root).population
+    }
+
+    /// Returns the number of generations the universe was advanced by.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+impl Default for HashLife {
+    fn default() -> HashLife {
+        HashLife::new()
+    }
+}
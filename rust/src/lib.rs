@@ -1,14 +1,28 @@
 // The mod keyword tells the compiler to look for the specified
 // file in the module tree.
 mod cell;
+pub mod census;
+pub mod cycle;
+pub mod hashlife;
+pub mod library;
+pub mod life106;
+pub mod neighborhood;
+pub mod packed;
+pub mod pattern;
+pub mod plaintext;
+pub mod rle;
+pub mod rng;
+pub mod rule;
+pub mod soup;
+pub mod sparse;
+pub mod timeline;
+pub mod topology;
+pub mod undo;
 pub mod universe;
 mod utils;
 
 use wasm_bindgen::prelude::*;
 
-// Crate to generate random numbers:
-use rand::Rng;
-
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
 #[cfg(feature = "wee_alloc")]
@@ -19,6 +33,7 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 extern crate web_sys;
 
 // A macro to provide `println!(..)`-style syntax for `console.log` logging.
+#[allow(unused_macros)]
 macro_rules! log {
     ( $( $t:tt )* ) => {
         web_sys::console::log_1(&format!( $( $t )* ).into());
@@ -125,4 +140,2098 @@ mod tests {
             cell::Cell::Dead
         );
     }
+
+    #[test]
+    /// Parses the rulestrings of a couple of well-known Life-like automata.
+    fn can_parse_rules() {
+        let high_life: rule::Rule = "B36/S23".parse().unwrap();
+        assert_eq!(high_life, rule::Rule::new(&[3, 6], &[2, 3]));
+
+        let seeds: rule::Rule = "B2/S".parse().unwrap();
+        assert_eq!(seeds.to_string(), "B2/S");
+
+        // The prefixes may come in any order and case:
+        let day_and_night: rule::Rule = "s34678/b3678".parse().unwrap();
+        assert_eq!(day_and_night.to_string(), "B3678/S34678");
+
+        // The S/B notation without prefixes lists survival first:
+        let life: rule::Rule = "23/3".parse().unwrap();
+        assert_eq!(life, rule::Rule::default());
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        assert_eq!("".parse::<rule::Rule>(), Err(rule::RuleError::Empty));
+        assert_eq!(
+            "B3S23".parse::<rule::Rule>(),
+            Err(rule::RuleError::MissingSlash)
+        );
+        assert_eq!(
+            "B39/S23".parse::<rule::Rule>(),
+            Err(rule::RuleError::CountOutOfRange(9))
+        );
+        assert_eq!(
+            "B3/23".parse::<rule::Rule>(),
+            Err(rule::RuleError::MixedNotation)
+        );
+        assert_eq!(
+            "B3/B23".parse::<rule::Rule>(),
+            Err(rule::RuleError::DuplicatePrefix('B'))
+        );
+        assert_eq!(
+            "B3x/S23".parse::<rule::Rule>(),
+            Err(rule::RuleError::UnexpectedCharacter('x'))
+        );
+    }
+
+    #[test]
+    /// Checks that a failed `set_rule` keeps the previous rule.
+    fn can_set_rule() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 64, 64);
+        assert_eq!(universe.rule(), "B3/S23");
+
+        universe.set_rule("B36/S23").unwrap();
+        assert_eq!(universe.rule(), "B36/S23");
+
+        assert!(universe.set_rule("B3/S239").is_err());
+        assert_eq!(universe.rule(), "B36/S23");
+    }
+
+    #[test]
+    /// In Seeds (`B2/S`) no cell survives, so two neighbouring cells
+    /// die while giving birth to the cells next to both of them.
+    fn tick_respects_rule() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.set_rule("B2/S").unwrap();
+        universe.toggle_cell(3, 3);
+        universe.toggle_cell(3, 4);
+        universe.tick();
+
+        let alive: Vec<usize> = universe
+            .get_cells()
+            .iter()
+            .enumerate()
+            .filter(|(_, &cell)| cell == cell::Cell::Alive)
+            .map(|(idx, _)| idx)
+            .collect();
+        let expected = vec![
+            universe.get_index(2, 3),
+            universe.get_index(2, 4),
+            universe.get_index(4, 3),
+            universe.get_index(4, 4),
+        ];
+        assert_eq!(alive, expected);
+    }
+
+    #[test]
+    /// Parses a glider in the RLE format, including its header and comments.
+    fn can_parse_rle() {
+        let glider = rle::parse(
+            "#N Glider
+#C The smallest spaceship.
+x = 3, y = 3, rule = B3/S23
+bob$2bo$3o!",
+        )
+        .unwrap();
+
+        assert_eq!(glider.name(), Some("Glider"));
+        assert_eq!(glider.comments(), ["The smallest spaceship."]);
+        assert_eq!(glider.rule(), Some(&rule::Rule::default()));
+        assert_eq!((glider.width(), glider.height()), (3, 3));
+        assert_eq!(
+            glider.live_cells().collect::<Vec<_>>(),
+            vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_rle() {
+        assert_eq!(
+            rle::parse("x = 3, y = 3\n0o$2o!"),
+            Err(pattern::PatternError::InvalidRunCount { line: 2 })
+        );
+        assert_eq!(
+            rle::parse("x = 3, y = 3\n3o$3"),
+            Err(pattern::PatternError::InvalidRunCount { line: 2 })
+        );
+        assert_eq!(
+            rle::parse("x = 3, y = 3\n3o$2k!"),
+            Err(pattern::PatternError::UnexpectedCharacter {
+                character: 'k',
+                line: 2
+            })
+        );
+        assert_eq!(
+            rle::parse("x = 2, y = 1\n3o!"),
+            Err(pattern::PatternError::ExceedsHeader {
+                width: 2,
+                height: 1
+            })
+        );
+        assert_eq!(
+            rle::parse("x = three, y = 3\n3o!"),
+            Err(pattern::PatternError::InvalidHeader { line: 1 })
+        );
+    }
+
+    #[test]
+    /// Inserts a blinker across the bottom right corner of the universe.
+    fn can_insert_rle_with_wrap() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.insert_rle(7, 6, "x = 3, y = 1\n3o!").unwrap();
+
+        for column in [6, 7, 0] {
+            assert_eq!(
+                universe.get_cells()[universe.get_index(7, column)],
+                cell::Cell::Alive
+            );
+        }
+        assert_eq!(
+            universe.insert_rle(0, 0, "x = 9, y = 1\n9o!"),
+            Err(pattern::PatternError::TooLarge {
+                width: 9,
+                height: 1
+            })
+        );
+    }
+
+    #[test]
+    /// Exports a glider, cropped to its bounding box.
+    fn can_export_rle() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 16, 16);
+        universe.insert_rle(5, 7, "bob$2bo$3o!").unwrap();
+        universe.set_rule("B36/S23").unwrap();
+
+        assert_eq!(universe.bounding_box(), Some((5, 7, 3, 3)));
+        assert_eq!(
+            universe.to_rle(),
+            "x = 3, y = 3, rule = B36/S23\nbo$2bo$3o!\n"
+        );
+    }
+
+    #[test]
+    /// Checks that empty rows are merged and long lines are wrapped.
+    fn can_export_long_rle() {
+        let mut pattern = pattern::Pattern::new(80, 4);
+        for column in (0..80).step_by(2) {
+            pattern.set(0, column, cell::Cell::Alive);
+        }
+        pattern.set(3, 0, cell::Cell::Alive);
+
+        let rle = rle::write(&pattern);
+        assert!(rle.lines().all(|line| line.len() <= 70));
+        assert!(rle.trim_end().ends_with("3$o!"));
+        // Reading the exported pattern again yields the same cells:
+        let parsed = rle::parse(&rle).unwrap();
+        assert_eq!(
+            parsed.live_cells().collect::<Vec<_>>(),
+            pattern.live_cells().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn empty_universe_exports_empty_rle() {
+        let universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        assert_eq!(universe.to_rle(), "x = 0, y = 0, rule = B3/S23\n!\n");
+    }
+
+    #[test]
+    /// Parses a beacon in the plaintext format, with a short second row.
+    fn can_parse_plaintext() {
+        let beacon = plaintext::parse(
+            "!Name: Beacon
+!A period 2 oscillator.
+OO
+O
+...O
+..OO",
+        )
+        .unwrap();
+
+        assert_eq!(beacon.name(), Some("Beacon"));
+        assert_eq!(beacon.comments(), ["A period 2 oscillator."]);
+        assert_eq!((beacon.width(), beacon.height()), (4, 4));
+        assert_eq!(beacon.population(), 6);
+        assert_eq!(
+            plaintext::write(&beacon),
+            "!Name: Beacon\n!A period 2 oscillator.\nOO..\nO...\n...O\n..OO\n"
+        );
+        assert_eq!(
+            plaintext::parse("O.\n.X"),
+            Err(pattern::PatternError::UnexpectedCharacter {
+                character: 'X',
+                line: 2
+            })
+        );
+    }
+
+    #[test]
+    /// Parses a glider in the Life 1.06 format with negative coordinates.
+    fn can_parse_life106() {
+        let glider = life106::parse("#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n").unwrap();
+        assert_eq!(
+            glider.live_cells().collect::<Vec<_>>(),
+            vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]
+        );
+        assert_eq!(
+            life106::write(&glider),
+            "#Life 1.06\n1 0\n2 1\n0 2\n1 2\n2 2\n"
+        );
+
+        assert_eq!(
+            life106::parse("0 0\n"),
+            Err(pattern::PatternError::InvalidHeader { line: 1 })
+        );
+        assert_eq!(
+            life106::parse("#Life 1.06\n0 0\n1\n"),
+            Err(pattern::PatternError::InvalidCoordinate { line: 3 })
+        );
+    }
+
+    #[test]
+    /// Moves a glider through all three formats and back into a universe.
+    fn formats_round_trip() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 16, 16);
+        universe.insert_rle(2, 3, "bob$2bo$3o!").unwrap();
+
+        let mut copy = universe::Universe::new(universe::UniverseOption::Dead, 16, 16);
+        copy.insert_plaintext(2, 3, &universe.to_plaintext())
+            .unwrap();
+        assert_eq!(copy.to_rle(), universe.to_rle());
+
+        let mut copy = universe::Universe::new(universe::UniverseOption::Dead, 16, 16);
+        copy.insert_life106(2, 3, &universe.to_life106()).unwrap();
+        assert!(copy.get_cells() == universe.get_cells());
+    }
+
+    #[test]
+    /// Runs the same universe in both storages, with a width that
+    /// doesn't fill the last word of each row.
+    fn packed_universe_matches_universe() {
+        for rule in ["B3/S23", "B36/S23", "B2/S"] {
+            let mut universe = universe::Universe::new(universe::UniverseOption::TwoSeven, 70, 33);
+            universe.set_rule(rule).unwrap();
+            let mut packed = packed::PackedUniverse::from_universe(&universe).unwrap();
+            assert_eq!(packed.rule(), rule);
+
+            for _ in 0..16 {
+                universe.tick();
+                packed.tick();
+                assert!(packed.to_universe().get_cells() == universe.get_cells());
+            }
+        }
+
+        // The packed rows always wrap around:
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.set_topology(topology::Topology::Plane).unwrap();
+        assert_eq!(
+            packed::PackedUniverse::from_universe(&universe).err(),
+            Some(rule::RuleError::Unsupported("B3/S23".to_string()))
+        );
+    }
+
+    #[test]
+    /// Checks the memory layout that the JavaScript renderer relies on.
+    fn packed_universe_layout() {
+        let mut packed = packed::PackedUniverse::new(70, 3);
+        packed.toggle_cell(1, 66);
+        assert!(packed.is_alive(1, 66));
+        assert_eq!(packed.population(), 1);
+        assert_eq!(packed.bytes_per_row(), 16);
+        assert_eq!(packed.cells_len(), 48);
+
+        let bytes = unsafe {
+            std::slice::from_raw_parts(packed.cells() as *const u8, packed.cells_len() as usize)
+        };
+        let byte = (packed.bytes_per_row() + 66 / 8) as usize;
+        assert_eq!(bytes[byte], 1 << (66 % 8));
+    }
+
+    #[test]
+    /// Ticks a blinker back and forth, which swaps both buffers twice.
+    fn tick_reuses_buffers() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.insert_rle(3, 2, "3o!").unwrap();
+        let start = universe.to_rle();
+        let cells = universe.cells();
+
+        universe.tick();
+        assert_ne!(universe.to_rle(), start);
+        universe.tick();
+        assert_eq!(universe.to_rle(), start);
+        // After two swaps we're back at the first buffer:
+        assert_eq!(universe.cells(), cells);
+    }
+
+    #[test]
+    /// Compares HashLife with the dense `Universe` on a glider, whose
+    /// 16 generations don't reach the edges of the universe.
+    fn hashlife_matches_universe() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 32, 32);
+        universe.insert_rle(4, 4, "bob$2bo$3o!").unwrap();
+        let mut hashlife = hashlife::HashLife::from_universe(&universe).unwrap();
+
+        for _ in 0..16 {
+            universe.tick();
+        }
+        hashlife.step_pow2(4).unwrap();
+
+        assert_eq!(hashlife.generation(), 16);
+        assert_eq!(hashlife.population(), 5);
+        assert!(hashlife.to_universe(0, 0, 32, 32) == universe);
+    }
+
+    #[test]
+    /// The R-pentomino stabilizes after 1103 generations with 116 cells,
+    /// six of which are gliders that escape to infinity.
+    fn hashlife_runs_r_pentomino() {
+        let mut hashlife = hashlife::HashLife::new();
+        for (x, y) in [(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)] {
+            hashlife.set_cell(x, y, true);
+        }
+        assert!(hashlife.is_alive(0, 1) && !hashlife.is_alive(0, 0));
+
+        hashlife.step(1103).unwrap();
+        assert_eq!(hashlife.generation(), 1103);
+        assert_eq!(hashlife.population(), 116);
+
+        // Jumping another 2^20 generations keeps the ash and the gliders:
+        hashlife.step_pow2(20).unwrap();
+        assert_eq!(hashlife.population(), 116);
+    }
+
+    #[test]
+    fn hashlife_rejects_b0() {
+        let mut hashlife = hashlife::HashLife::new();
+        assert_eq!(
+            hashlife.set_rule("B0/S8"),
+            Err(rule::RuleError::Unsupported("B0/S8".to_string()))
+        );
+        assert_eq!(hashlife.rule(), "B3/S23");
+    }
+
+    #[test]
+    /// Steps that would need a root wider than the coordinates
+    /// reach, or overflow the generation counter, are rejected.
+    fn hashlife_rejects_huge_steps() {
+        let mut hashlife = hashlife::HashLife::new();
+        for x in -1..=1 {
+            hashlife.set_cell(x, 0, true);
+        }
+        assert_eq!(
+            hashlife.step_pow2(62),
+            Err(hashlife::HashLifeError::StepTooLarge { k: 62 })
+        );
+        assert_eq!(hashlife.generation(), 0);
+
+        hashlife.step_pow2(60).unwrap();
+        assert_eq!(hashlife.generation(), 1 << 60);
+        assert_eq!(hashlife.population(), 3);
+        assert!(hashlife.is_alive(-1, 0) && hashlife.is_alive(1, 0));
+        assert_eq!(
+            hashlife.step(u64::MAX),
+            Err(hashlife::HashLifeError::GenerationOverflow)
+        );
+        assert_eq!(hashlife.generation(), 1 << 60);
+    }
+
+    #[test]
+    fn can_tick_n() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 16, 16);
+        universe.insert_rle(1, 1, "bob$2bo$3o!").unwrap();
+        let mut copy = universe::Universe::new(universe::UniverseOption::Dead, 16, 16);
+        copy.insert_rle(1, 1, "bob$2bo$3o!").unwrap();
+
+        universe.tick_n(12);
+        for _ in 0..12 {
+            copy.tick();
+        }
+        assert!(universe == copy);
+    }
+
+    #[test]
+    /// Runs universes until each of the stop conditions is met.
+    fn can_run_until() {
+        use universe::StopCondition;
+
+        // A single cell dies of loneliness within one generation:
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.toggle_cell(4, 4);
+        assert_eq!(universe.run_until(100, StopCondition::Extinct, 0), 1);
+        // And an empty universe is extinct from the start:
+        assert_eq!(universe.run_until(100, StopCondition::Extinct, 0), 0);
+
+        // A blinker is back at its first phase after two generations:
+        universe.insert_rle(3, 3, "3o!").unwrap();
+        assert_eq!(universe.run_until(100, StopCondition::Repeats, 0), 2);
+
+        // A blinker never has more than 3 cells:
+        assert_eq!(
+            universe.run_until(10, StopCondition::PopulationExceeds, 3),
+            10
+        );
+
+        // The R-pentomino quickly grows beyond 5 cells:
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 32, 32);
+        universe.insert_rle(14, 14, "b2o$2o$bo!").unwrap();
+        assert_eq!(
+            universe.run_until(100, StopCondition::PopulationExceeds, 5),
+            1
+        );
+    }
+
+    #[test]
+    /// Follows the statistics of a blinker through a couple of edits and ticks.
+    fn tracks_stats() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.insert_rle(3, 2, "3o!").unwrap();
+        assert_eq!(universe.population(), 3);
+
+        universe.tick();
+        assert_eq!(
+            universe.stats(),
+            universe::Stats {
+                generation: 1,
+                population: 3,
+                births: 2,
+                deaths: 2,
+            }
+        );
+
+        universe.toggle_cell(0, 0);
+        assert_eq!(universe.population(), 4);
+        universe.tick();
+        assert_eq!(universe.generation(), 2);
+        // The lonely cell dies besides the blinker's two deaths:
+        assert_eq!((universe.births(), universe.deaths()), (2, 3));
+        assert_eq!(universe.population(), 3);
+
+        let universe = universe::Universe::new(universe::UniverseOption::TwoSeven, 64, 64);
+        let living = universe
+            .get_cells()
+            .iter()
+            .filter(|&&cell| cell == cell::Cell::Alive)
+            .count();
+        assert_eq!(universe.population() as usize, living);
+    }
+
+    #[test]
+    /// Detects still lifes, oscillators and universes that died out.
+    fn detects_stability() {
+        use cycle::{Oscillation, Stability};
+
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 16, 16);
+        universe.insert_rle(2, 2, "2o$2o!").unwrap();
+        assert_eq!(universe.stability(), Stability::Evolving);
+        universe.tick();
+        assert_eq!(universe.stability(), Stability::Still);
+        assert_eq!(
+            universe.oscillation(),
+            Some(Oscillation {
+                period: 1,
+                first_seen_generation: 0
+            })
+        );
+
+        // Adding a pulsar (period 3) starts the detection over:
+        universe
+            .insert_rle(
+                1,
+                1,
+                "2b3o3b3o2b2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2b2$2b3o3b3o2b$o4bob\
+                 o4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+            )
+            .unwrap();
+        assert_eq!(universe.oscillation(), None);
+        universe.tick_n(3);
+        assert_eq!(universe.stability(), Stability::Oscillating);
+        assert_eq!(universe.oscillation().unwrap().period, 3);
+
+        // A history that is too short can't see the period:
+        universe.set_history_capacity(2);
+        universe.tick_n(10);
+        assert_eq!(universe.stability(), Stability::Evolving);
+
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.toggle_cell(1, 1);
+        universe.tick();
+        assert_eq!(universe.stability(), Stability::Extinct);
+    }
+
+    #[test]
+    /// Resolves positions beyond the edges of a 4x3 grid.
+    fn resolves_topologies() {
+        use topology::Topology;
+
+        assert_eq!(Topology::Torus.resolve(-1, 4, 4, 3), Some((2, 0)));
+        assert_eq!(Topology::Plane.resolve(-1, 2, 4, 3), None);
+        assert_eq!(Topology::Plane.resolve(1, 2, 4, 3), Some((1, 2)));
+        // Leaving at the top mirrors the column:
+        assert_eq!(Topology::KleinBottle.resolve(-1, 0, 4, 3), Some((2, 3)));
+        assert_eq!(Topology::KleinBottle.resolve(1, -1, 4, 3), Some((1, 3)));
+        // Leaving at the right mirrors the row:
+        assert_eq!(Topology::CrossSurface.resolve(0, 4, 4, 3), Some((2, 0)));
+        assert_eq!(
+            Topology::HorizontalCylinder.resolve(0, 4, 4, 3),
+            Some((0, 0))
+        );
+        assert_eq!(Topology::HorizontalCylinder.resolve(3, 0, 4, 3), None);
+        assert_eq!(Topology::VerticalCylinder.resolve(3, 0, 4, 3), Some((0, 0)));
+        assert_eq!(Topology::VerticalCylinder.resolve(0, -1, 4, 3), None);
+    }
+
+    #[test]
+    /// A glider hits the dead border of a plane and turns into a block,
+    /// while on a torus it keeps on flying.
+    fn glider_crashes_on_plane() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.set_topology(topology::Topology::Plane).unwrap();
+        universe.insert_rle(2, 2, "bob$2bo$3o!").unwrap();
+        universe.tick_n(40);
+        assert_eq!(universe.to_rle(), "x = 2, y = 2, rule = B3/S23\n2o$2o!\n");
+        assert_eq!(universe.bounding_box(), Some((6, 6, 2, 2)));
+
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.insert_rle(2, 2, "bob$2bo$3o!").unwrap();
+        universe.tick_n(32);
+        assert_eq!(universe.population(), 5);
+        assert_eq!(universe.bounding_box(), Some((2, 2, 3, 3)));
+    }
+
+    #[test]
+    /// Patterns and gliders inserted at an edge of a plane are cut off.
+    fn insertion_respects_topology() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.set_topology(topology::Topology::Plane).unwrap();
+        universe.insert_rle(7, 6, "3o!").unwrap();
+        assert_eq!(universe.population(), 2);
+
+        universe.toggle_glider(0, 0);
+        // Only the cells right, below and below right of the corner remain:
+        assert_eq!(universe.population(), 5);
+
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.toggle_glider(0, 0);
+        assert_eq!(universe.population(), 5);
+    }
+
+    #[test]
+    /// Parses rulestrings with a neighbourhood suffix and in the HROT
+    /// notation and prints them in their canonical form.
+    fn parses_neighborhood_rules() {
+        use neighborhood::NeighborhoodKind;
+
+        let rule: rule::Rule = "b2/s34h".parse().unwrap();
+        assert_eq!(rule.neighborhood().kind(), NeighborhoodKind::Hexagonal);
+        assert_eq!(rule.to_string(), "B2/S34H");
+        assert_eq!(
+            "B1/S012V".parse::<rule::Rule>().unwrap().to_string(),
+            "B1/S012V"
+        );
+
+        let rule: rule::Rule = "R2,C2,S6-9,B7,8,NM".parse().unwrap();
+        assert_eq!(rule.neighborhood().range(), 2);
+        assert!(rule.is_birth(8) && !rule.is_birth(9));
+        assert_eq!(rule.to_string(), "R2,C2,S6-9,B7-8,NM");
+        assert_eq!(
+            "R3,C0,S2,B3,NN".parse::<rule::Rule>().unwrap().to_string(),
+            "R3,C2,S2,B3,NN"
+        );
+
+        assert_eq!(
+            "B7/S23H".parse::<rule::Rule>(),
+            Err(rule::RuleError::CountOutOfRange(7))
+        );
+        assert_eq!(
+            "R1,C2,S2,B13,NM".parse::<rule::Rule>(),
+            Err(rule::RuleError::CountOutOfRange(13))
+        );
+        assert_eq!(
+            "R0,C2,S2,B3,NM".parse::<rule::Rule>(),
+            Err(rule::RuleError::InvalidRange(0))
+        );
+        assert_eq!(
+            "R2,C2,S2,B3,NX".parse::<rule::Rule>(),
+            Err(rule::RuleError::InvalidToken("NX".to_string()))
+        );
+    }
+
+    #[test]
+    /// The neighbourhoods have the expected sizes and the hexagonal one
+    /// depends on whether the row is shifted.
+    fn neighborhood_offsets() {
+        use neighborhood::{Neighborhood, NeighborhoodKind};
+
+        for (kind, range, size) in [
+            (NeighborhoodKind::Moore, 2, 24),
+            (NeighborhoodKind::VonNeumann, 1, 4),
+            (NeighborhoodKind::VonNeumann, 2, 12),
+            (NeighborhoodKind::Hexagonal, 1, 6),
+            (NeighborhoodKind::Hexagonal, 2, 18),
+        ] {
+            let neighborhood = Neighborhood::new(kind, range);
+            assert_eq!(neighborhood.size(), size);
+            assert_eq!(neighborhood.offsets(0).len(), size as usize);
+            assert_eq!(neighborhood.offsets(1).len(), size as usize);
+        }
+
+        let mut even = Neighborhood::new(NeighborhoodKind::Hexagonal, 1).offsets(0);
+        even.sort_unstable();
+        assert_eq!(even, [(-1, -1), (-1, 0), (0, -1), (0, 1), (1, -1), (1, 0)]);
+        let mut odd = Neighborhood::new(NeighborhoodKind::Hexagonal, 1).offsets(1);
+        odd.sort_unstable();
+        assert_eq!(odd, [(-1, 0), (-1, 1), (0, -1), (0, 1), (1, 0), (1, 1)]);
+    }
+
+    #[test]
+    /// A single cell under a `B1/S` rule is replaced by its
+    /// whole neighbourhood.
+    fn single_cell_fills_neighborhood() {
+        for (rule, population) in [
+            ("B1/SV", 4),
+            ("B1/SH", 6),
+            ("R2,C2,S,B1,NM", 24),
+            ("R2,C2,S,B1,NN", 12),
+        ] {
+            let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 10, 10);
+            universe.set_rule(rule).unwrap();
+            universe.toggle_cell(4, 4);
+            universe.tick();
+            assert_eq!(universe.population(), population, "{}", rule);
+            assert_eq!(universe.rule(), rule);
+        }
+    }
+
+    #[test]
+    /// Switching the neighbourhood keeps the conditions, as long as
+    /// they fit into the new neighbourhood.
+    fn sets_neighborhood() {
+        use neighborhood::NeighborhoodKind;
+
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 4, 4);
+        universe.set_rule("B2/S34").unwrap();
+        universe
+            .set_neighborhood(NeighborhoodKind::Hexagonal, 1)
+            .unwrap();
+        assert_eq!(universe.rule(), "B2/S34H");
+        assert_eq!(universe.neighborhood_kind(), NeighborhoodKind::Hexagonal);
+        assert_eq!(
+            universe.set_neighborhood(NeighborhoodKind::VonNeumann, 0),
+            Err(rule::RuleError::InvalidRange(0))
+        );
+        universe
+            .set_neighborhood(NeighborhoodKind::Moore, 3)
+            .unwrap();
+        assert_eq!(universe.rule(), "R3,C2,S3-4,B2,NM");
+        assert_eq!(universe.neighborhood_range(), 3);
+
+        universe.set_rule("B3/S5678").unwrap();
+        assert_eq!(
+            universe.set_neighborhood(NeighborhoodKind::VonNeumann, 1),
+            Err(rule::RuleError::CountOutOfRange(8))
+        );
+        assert_eq!(universe.rule(), "B3/S5678");
+    }
+
+    #[test]
+    /// Engines that are specialized on the 8 cell neighbourhood
+    /// reject all other ones.
+    fn specialized_engines_reject_neighborhoods() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 4, 4);
+        universe.set_rule("B2/S34H").unwrap();
+        assert_eq!(
+            packed::PackedUniverse::from_universe(&universe).err(),
+            Some(rule::RuleError::Unsupported("B2/S34H".to_string()))
+        );
+        assert!(hashlife::HashLife::from_universe(&universe).is_err());
+        assert!(hashlife::HashLife::new()
+            .set_rule("R2,C2,S2,B3,NM")
+            .is_err());
+    }
+
+    #[test]
+    /// Hexagonal universes are rendered with every odd row shifted.
+    fn renders_hexagonal_grid() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 3, 2);
+        universe.set_rule("B2/S34H").unwrap();
+        universe.toggle_cell(1, 2);
+        assert_eq!(universe.render(), "◻ ◻ ◻\n ◻ ◻ ◼\n");
+    }
+
+    #[test]
+    /// Rulestrings in the HROT notation survive the RLE header,
+    /// even though they contain commas.
+    fn rle_header_with_hrot_rule() {
+        let pattern = rle::parse("x = 1, y = 1, rule = R2,C2,S6-9,B7-8,NM\no!").unwrap();
+        assert_eq!(
+            pattern.rule().map(|rule| rule.to_string()),
+            Some("R2,C2,S6-9,B7-8,NM".to_string())
+        );
+        assert_eq!(
+            rle::write(&pattern),
+            "x = 1, y = 1, rule = R2,C2,S6-9,B7-8,NM\no!\n"
+        );
+    }
+
+    #[test]
+    /// Parses Bosco's Rule, in which a living cell counts itself.
+    fn parses_larger_than_life_rules() {
+        let rule: rule::Rule = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+        assert!(rule.includes_middle());
+        assert!(!rule.is_life_like());
+        assert_eq!(rule.neighborhood().size(), 120);
+        // The survival conditions include the cell itself:
+        assert!(rule.is_survival(33) && rule.is_survival(57));
+        assert!(!rule.is_survival(32) && !rule.is_survival(58));
+        assert!(rule.is_birth(34) && !rule.is_birth(33));
+        assert_eq!(rule.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+
+        // Without the middle cell, the rule is written in the HROT notation:
+        assert_eq!(
+            "R5,C0,M0,S34..58,B34..45,NM"
+                .parse::<rule::Rule>()
+                .unwrap()
+                .to_string(),
+            "R5,C2,S34-58,B34-45,NM"
+        );
+        // A living cell can have 121 living cells in its neighbourhood:
+        assert!("R5,C0,M1,S121,B1,NM".parse::<rule::Rule>().is_ok());
+        assert_eq!(
+            "R5,C0,M0,S121,B1,NM".parse::<rule::Rule>(),
+            Err(rule::RuleError::CountOutOfRange(121))
+        );
+        assert_eq!(
+            "R5,C0,M2,S1,B1,NM".parse::<rule::Rule>(),
+            Err(rule::RuleError::InvalidToken("M2".to_string()))
+        );
+    }
+
+    #[test]
+    /// The summed-area table counts the same neighbours as counting
+    /// them one by one, on all kinds of topologies.
+    fn larger_than_life_tick_matches_direct_count() {
+        use topology::Topology;
+
+        let rulestring = "R3,C0,M1,S9..21,B10..14,NM";
+        let rule: rule::Rule = rulestring.parse().unwrap();
+        for topology in [Topology::Torus, Topology::Plane, Topology::CrossSurface] {
+            let mut universe = universe::Universe::new(universe::UniverseOption::Random, 13, 9);
+            universe.set_rule(rulestring).unwrap();
+            universe.set_topology(topology).unwrap();
+
+            let mut expected = Vec::new();
+            for row in 0..universe.height() {
+                for column in 0..universe.width() {
+                    let cell = universe.get_cells()[universe.get_index(row, column)];
+                    let live_neighbors = universe.live_neighbor_count(row, column);
+                    expected.push(rule.next_cell(cell, live_neighbors));
+                }
+            }
+
+            universe.tick();
+            assert_eq!(universe.get_cells(), &expected, "{:?}", topology);
+        }
+    }
+
+    #[test]
+    /// Parses Generations rules in the B/S/C and the S/B/C notation.
+    fn parses_generations_rules() {
+        let rule: rule::Rule = "/2/3".parse().unwrap();
+        assert_eq!(rule.states(), 3);
+        assert!(!rule.is_life_like());
+        assert_eq!(rule.to_string(), "B2/S/C3");
+        assert_eq!(
+            "345/2/4".parse::<rule::Rule>().unwrap().to_string(),
+            "B2/S345/C4"
+        );
+        assert_eq!(
+            "b2/s/g3h".parse::<rule::Rule>().unwrap().to_string(),
+            "B2/S/C3H"
+        );
+        assert_eq!(
+            "R2,C5,S2-3,B3,NN"
+                .parse::<rule::Rule>()
+                .unwrap()
+                .to_string(),
+            "R2,C5,S2-3,B3,NN"
+        );
+        assert_eq!("B3/S23/C2".parse::<rule::Rule>(), Ok(rule::Rule::default()));
+
+        assert_eq!(
+            "B2/S/C1".parse::<rule::Rule>(),
+            Err(rule::RuleError::InvalidStates(1))
+        );
+        assert_eq!(
+            "R2,C256,S2,B3,NM".parse::<rule::Rule>(),
+            Err(rule::RuleError::InvalidStates(256))
+        );
+        assert_eq!(
+            "B2/S/3/4".parse::<rule::Rule>(),
+            Err(rule::RuleError::InvalidToken("B2/S/3/4".to_string()))
+        );
+    }
+
+    #[test]
+    /// In Brian's Brain, every living cell dies after a single tick
+    /// and passes through one dying state.
+    fn brians_brain_cells_are_dying() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 6, 6);
+        universe.set_rule("/2/3").unwrap();
+        assert_eq!(universe.state_count(), 3);
+        universe.toggle_cell(2, 2);
+        universe.toggle_cell(2, 3);
+
+        universe.tick();
+        assert_eq!(universe.stats().births, 4);
+        assert_eq!(universe.stats().deaths, 2);
+        assert_eq!(universe.population(), 4);
+        let dying = universe.get_dying_cells().unwrap();
+        assert_eq!(dying[universe.get_index(2, 2)], 1);
+        assert_eq!(dying[universe.get_index(2, 3)], 1);
+        assert_eq!(dying.iter().filter(|&&stage| stage > 0).count(), 2);
+
+        // The dying cells are dead after the next tick:
+        universe.tick();
+        let dying = universe.get_dying_cells().unwrap();
+        assert_eq!(dying[universe.get_index(2, 2)], 0);
+        assert_eq!(
+            universe.get_cells()[universe.get_index(2, 2)],
+            cell::Cell::Dead
+        );
+    }
+
+    #[test]
+    /// A lonely cell in Star Wars passes through both dying states, while
+    /// dying cells can be revived by hand and are dropped with the rule.
+    fn star_wars_dying_stages() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 5, 5);
+        universe.set_rule("345/2/4").unwrap();
+        universe.toggle_cell(2, 2);
+        let idx = universe.get_index(2, 2);
+
+        for stage in [1, 2, 0] {
+            universe.tick();
+            assert_eq!(universe.get_dying_cells().unwrap()[idx], stage);
+            assert_eq!(universe.population(), 0);
+        }
+
+        universe.toggle_cell(2, 2);
+        universe.tick();
+        universe.toggle_cell(2, 2);
+        assert_eq!(universe.get_dying_cells().unwrap()[idx], 0);
+        assert_eq!(universe.population(), 1);
+
+        universe.tick();
+        universe.tick();
+        assert_eq!(universe.get_dying_cells().unwrap()[idx], 2);
+        universe.set_rule("/2/3").unwrap();
+        assert_eq!(universe.get_dying_cells().unwrap()[idx], 0);
+
+        assert_eq!(
+            packed::PackedUniverse::from_universe(&universe).err(),
+            Some(rule::RuleError::Unsupported("B2/S/C3".to_string()))
+        );
+    }
+
+    #[test]
+    /// A blinker's center cell keeps getting older, while its
+    /// other cells are reborn every other tick.
+    fn tracks_cell_ages() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 5, 5);
+        assert!(universe.get_ages().is_none());
+        assert!(universe.ages().is_null());
+
+        universe.insert_rle(2, 1, "3o!").unwrap();
+        universe.set_age_tracking(true);
+        assert!(universe.is_tracking_ages());
+        universe.tick_n(3);
+
+        let ages = universe.get_ages().unwrap();
+        assert_eq!(ages[universe.get_index(2, 2)], 3);
+        assert_eq!(ages[universe.get_index(1, 2)], 0);
+        assert_eq!(ages[universe.get_index(2, 1)], 0);
+        assert_eq!(ages[universe.get_index(0, 0)], 3);
+
+        // Editing a cell starts its age over:
+        universe.toggle_cell(0, 0);
+        assert_eq!(universe.get_ages().unwrap()[0], 0);
+
+        universe.set_age_tracking(false);
+        assert!(universe.get_ages().is_none());
+    }
+
+    #[test]
+    /// The age of a cell saturates instead of wrapping around.
+    fn cell_ages_saturate() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 4, 4);
+        universe.insert_rle(1, 1, "2o$2o!").unwrap();
+        universe.set_age_tracking(true);
+        universe.tick_n(u16::MAX as u32 + 10);
+        assert!(universe
+            .get_ages()
+            .unwrap()
+            .iter()
+            .all(|&age| age == u16::MAX));
+    }
+
+    #[test]
+    /// SplitMix64 produces the reference values of its original
+    /// implementation.
+    fn splitmix64_reference_values() {
+        let mut rng = rng::SplitMix64::new(0);
+        assert_eq!(rng.next_u64(), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(rng.next_u64(), 0x6E78_9E6A_A1B9_65F4);
+        assert_eq!(rng.next_u64(), 0x06C4_5D18_8009_454F);
+    }
+
+    #[test]
+    /// The same seed gives the same universe, while the density
+    /// determines how many cells are alive.
+    fn seeded_random_universes() {
+        let universe = universe::Universe::new_random(42, 0.3, 100, 100);
+        assert!(universe == universe::Universe::new_random(42, 0.3, 100, 100));
+        assert!(universe != universe::Universe::new_random(43, 0.3, 100, 100));
+        assert!((2700..3300).contains(&universe.population()));
+
+        assert_eq!(
+            universe::Universe::new_random(7, 0.0, 16, 16).population(),
+            0
+        );
+        assert_eq!(
+            universe::Universe::new_random(7, 1.0, 16, 16).population(),
+            256
+        );
+    }
+
+    #[test]
+    /// Every soup is left unchanged by the transformations
+    /// of its symmetry.
+    fn soups_are_symmetric() {
+        use soup::Symmetry;
+
+        let symmetries = [
+            Symmetry::C1,
+            Symmetry::C2_1,
+            Symmetry::C2_2,
+            Symmetry::C2_4,
+            Symmetry::C4_1,
+            Symmetry::C4_4,
+            Symmetry::D2_Plus1,
+            Symmetry::D2_Plus2,
+            Symmetry::D2_X,
+            Symmetry::D4_Plus1,
+            Symmetry::D4_Plus2,
+            Symmetry::D4_Plus4,
+            Symmetry::D4_X1,
+            Symmetry::D4_X4,
+            Symmetry::D8_1,
+            Symmetry::D8_4,
+        ];
+        for symmetry in symmetries {
+            let soup = soup::generate(symmetry, 1234);
+            let (width, height) = symmetry.size();
+            assert_eq!((soup.width(), soup.height()), (width, height));
+            assert_eq!(soup, soup::generate(symmetry, 1234));
+
+            let name = symmetry.name();
+            for (row, column) in soup.live_cells() {
+                let (bottom, right) = (height - 1 - row, width - 1 - column);
+                let mut images = vec![];
+                if name.starts_with('C') && name != "C1"
+                    || name.starts_with("D4")
+                    || name.starts_with("D8")
+                {
+                    images.push((bottom, right));
+                }
+                if name.starts_with("C4") || name.starts_with("D8") {
+                    images.push((column, width - 1 - row));
+                }
+                if name.contains('+') || name.starts_with("D8") {
+                    images.push((bottom, column));
+                }
+                if name.contains('x') || name.starts_with("D8") {
+                    images.push((column, row));
+                }
+                for (image_row, image_column) in images {
+                    assert_eq!(
+                        soup.get(image_row, image_column),
+                        cell::Cell::Alive,
+                        "{}",
+                        name
+                    );
+                }
+            }
+        }
+
+        // Asymmetric soups come from the same 50 % chance:
+        let soup = soup::generate(Symmetry::C1, 99);
+        assert!((64..192).contains(&soup.population()));
+        assert_ne!(soup, soup::generate(Symmetry::C1, 100));
+    }
+
+    #[test]
+    /// Soups are placed in the center of a dead universe.
+    fn soup_universe_is_centered() {
+        let universe = universe::Universe::new_soup(soup::Symmetry::D8_4, 7, 64, 48).unwrap();
+        let (row, column, width, height) = universe.bounding_box().unwrap();
+        assert!(row >= 16 && row + height <= 32);
+        assert!(column >= 24 && column + width <= 40);
+        assert_eq!(
+            universe.population(),
+            soup::generate(soup::Symmetry::D8_4, 7).population()
+        );
+
+        assert!(universe::Universe::new_soup(soup::Symmetry::C1, 7, 8, 8).is_err());
+
+        // The soup itself can't be undone:
+        let mut universe = universe;
+        universe.set_undo_budget(1 << 20);
+        universe.toggle_cell(0, 0);
+        assert!(universe.undo());
+        assert!(!universe.undo());
+        assert_eq!(
+            universe.population(),
+            soup::generate(soup::Symmetry::D8_4, 7).population()
+        );
+    }
+
+    #[test]
+    /// Common objects get their well-known apgcodes, no matter
+    /// how they're oriented or which phase they're in.
+    fn classifies_common_objects() {
+        let rule = rule::Rule::default();
+        let classify = |rle: &str| {
+            let pattern = rle::parse(rle).unwrap();
+            let cells: Vec<(i64, i64)> = pattern
+                .live_cells()
+                .map(|(row, column)| (row as i64, column as i64))
+                .collect();
+            census::classify(&cells, &rule)
+        };
+
+        assert_eq!(classify("2o$2o!"), "xs4_33");
+        assert_eq!(classify("b2o$o2bo$b2o!"), "xs6_696");
+        assert_eq!(classify("bo$obo$obo$bo!"), "xs6_696");
+        assert_eq!(classify("3o!"), "xp2_7");
+        assert_eq!(classify("o$o$o!"), "xp2_7");
+        assert_eq!(classify("bo$2bo$3o!"), "xq4_153");
+        assert_eq!(classify("obo$b2o$bo!"), "xq4_153");
+        assert_eq!(classify("bo2bo$o4b$o3bo$4o!"), "xq4_6frc");
+        // The R-pentomino takes far longer than `MAX_PERIOD` to settle:
+        assert_eq!(classify("b2o$2o$bo!"), census::PATHOLOGICAL);
+    }
+
+    #[test]
+    /// A census counts separate objects, also across the edges
+    /// of a torus.
+    fn takes_census() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 32, 32);
+        universe.insert_rle(5, 5, "2o$2o!").unwrap();
+        universe.insert_rle(5, 20, "2o$2o!").unwrap();
+        universe.insert_rle(10, 10, "3o!").unwrap();
+        universe.insert_rle(20, 20, "bo$2bo$3o!").unwrap();
+        // A block that is split by the bottom right corner:
+        universe.toggle_cell(31, 31);
+        universe.toggle_cell(31, 0);
+        universe.toggle_cell(0, 31);
+        universe.toggle_cell(0, 0);
+
+        let census = universe.census();
+        assert_eq!(census.count("xs4_33"), 3);
+        assert_eq!(census.count("xp2_7"), 1);
+        assert_eq!(census.count("xq4_153"), 1);
+        assert_eq!(census.total(), 5);
+        assert_eq!(census.render(), "xs4_33 3\nxp2_7 1\nxq4_153 1\n");
+    }
+
+    #[test]
+    /// The extended Wechsler format shortens runs of empty columns
+    /// and splits tall objects into strips.
+    fn classifies_larger_objects() {
+        let rule = rule::Rule::default();
+        // Two blocks far apart form a single still life if we ask for it.
+        // Stacking them is shorter than a run of 5 empty columns (`y1`):
+        let cells = [
+            (0, 0),
+            (0, 1),
+            (1, 0),
+            (1, 1),
+            (0, 7),
+            (0, 8),
+            (1, 7),
+            (1, 8),
+        ];
+        assert_eq!(census::classify(&cells, &rule), "xs8_33zcc");
+        // The pentadecathlon is taller than a single strip:
+        let pentadecathlon = rle::parse("2bo4bo$2ob4ob2o$2bo4bo!").unwrap();
+        let cells: Vec<(i64, i64)> = pentadecathlon
+            .live_cells()
+            .map(|(row, column)| (row as i64, column as i64))
+            .collect();
+        assert_eq!(census::classify(&cells, &rule), "xp15_4r4z4r4");
+    }
+
+    #[test]
+    /// All built-in patterns can be parsed and behave as expected.
+    fn builtin_patterns() {
+        let rule = rule::Rule::default();
+        let classify = |name: &str| {
+            let cells: Vec<(i64, i64)> = library::get(name)
+                .unwrap()
+                .live_cells()
+                .map(|(row, column)| (row as i64, column as i64))
+                .collect();
+            census::classify(&cells, &rule)
+        };
+
+        assert_eq!(library::names().count(), 10);
+        assert_eq!(library::builtin_patterns().len(), 10);
+        for name in library::names() {
+            let pattern = library::get(name).unwrap();
+            assert_eq!(pattern.name(), Some(name));
+            assert!(pattern.rule().is_none());
+        }
+
+        assert_eq!(classify("glider"), "xq4_153");
+        assert_eq!(classify("LWSS"), "xq4_6frc");
+        assert!(classify("mwss").starts_with("xq4_"));
+        assert!(classify("hwss").starts_with("xq4_"));
+        assert!(classify("pulsar").starts_with("xp3_"));
+        assert!(classify("pentadecathlon").starts_with("xp15_"));
+        assert_eq!(library::get("gosper-glider-gun").unwrap().population(), 36);
+        assert_eq!(library::get("acorn").unwrap().population(), 7);
+        assert_eq!(
+            library::get("unicorn"),
+            Err(pattern::PatternError::UnknownPattern("unicorn".to_string()))
+        );
+
+        // The diehard vanishes after 130 generations:
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 64, 64);
+        universe
+            .place_pattern("diehard", 30, 28, pattern::Orientation::Original)
+            .unwrap();
+        assert_eq!(
+            universe.run_until(200, universe::StopCondition::Extinct, 0),
+            130
+        );
+    }
+
+    #[test]
+    /// Placing a pattern rotates or mirrors it and wraps
+    /// around the edges of a torus.
+    fn places_oriented_patterns() {
+        use pattern::Orientation;
+
+        let pattern = rle::parse("3o$o!").unwrap();
+        let oriented = |orientation| rle::write(&pattern.oriented(orientation));
+        assert_eq!(
+            oriented(Orientation::Original),
+            "x = 3, y = 2, rule = B3/S23\n3o$o!\n"
+        );
+        assert_eq!(
+            oriented(Orientation::Rotate90),
+            "x = 2, y = 3, rule = B3/S23\n2o$bo$bo!\n"
+        );
+        assert_eq!(
+            oriented(Orientation::Rotate180),
+            "x = 3, y = 2, rule = B3/S23\n2bo$3o!\n"
+        );
+        assert_eq!(
+            oriented(Orientation::Rotate270),
+            "x = 2, y = 3, rule = B3/S23\no$o$2o!\n"
+        );
+        assert_eq!(
+            oriented(Orientation::FlipHorizontal),
+            "x = 3, y = 2, rule = B3/S23\n3o$2bo!\n"
+        );
+        assert_eq!(
+            oriented(Orientation::FlipVertical),
+            "x = 3, y = 2, rule = B3/S23\no$3o!\n"
+        );
+        assert_eq!(
+            oriented(Orientation::Transpose),
+            "x = 2, y = 3, rule = B3/S23\n2o$o$o!\n"
+        );
+        assert_eq!(
+            oriented(Orientation::AntiTranspose),
+            "x = 2, y = 3, rule = B3/S23\nbo$bo$2o!\n"
+        );
+
+        // A glider placed across the bottom right corner:
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe
+            .place_pattern("glider", 7, 7, Orientation::Rotate180)
+            .unwrap();
+        assert_eq!(universe.population(), 5);
+        let glider = library::get("glider")
+            .unwrap()
+            .oriented(Orientation::Rotate180);
+        for (row, column) in glider.live_cells() {
+            let idx = universe.get_index((7 + row) % 8, (7 + column) % 8);
+            assert_eq!(universe.get_cells()[idx], cell::Cell::Alive);
+        }
+        assert!(universe
+            .place_pattern("glider", 0, 0, Orientation::Original)
+            .is_ok());
+        assert!(universe
+            .place_pattern("spaceship", 0, 0, Orientation::Original)
+            .is_err());
+    }
+
+    #[test]
+    /// Patterns wrap around their own edges when they're translated.
+    fn translates_patterns() {
+        let pattern = rle::parse("x = 4, y = 2\n2o!").unwrap();
+        let translated = pattern.translated(1, 3);
+        assert_eq!(
+            translated.live_cells().collect::<Vec<(u32, u32)>>(),
+            [(1, 0), (1, 3)]
+        );
+        assert_eq!(pattern.translated(-2, 4), pattern);
+    }
+
+    #[test]
+    /// Rotating a region turns a glider around and keeps
+    /// the rest of the universe as it is.
+    fn transforms_regions() {
+        use pattern::Orientation;
+
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 10, 10);
+        universe.insert_rle(1, 1, "bo$2bo$3o!").unwrap();
+        universe.toggle_cell(8, 8);
+        universe
+            .transform_region(1, 1, 3, 3, Orientation::Rotate180)
+            .unwrap();
+        assert_eq!(universe.region(1, 1, 3, 3), {
+            let mut glider = rle::parse("3o$o$bo!").unwrap();
+            glider.set_rule(Some(rule::Rule::default()));
+            glider
+        });
+        assert_eq!(universe.population(), 6);
+
+        // The glider now heads to the top left and the lone cell dies:
+        universe.tick_n(4);
+        assert_eq!(universe.bounding_box(), Some((0, 0, 3, 3)));
+        assert_eq!(universe.region(0, 0, 3, 3), {
+            let mut glider = rle::parse("3o$o$bo!").unwrap();
+            glider.set_rule(Some(rule::Rule::default()));
+            glider
+        });
+
+        // A 3x1 region rotated by 90° becomes a 1x3 one:
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 6, 6);
+        universe.insert_rle(0, 0, "3o!").unwrap();
+        universe
+            .transform_region(0, 0, 3, 1, Orientation::Rotate90)
+            .unwrap();
+        assert_eq!(universe.to_rle(), "x = 1, y = 3, rule = B3/S23\no$o$o!\n");
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 6, 4);
+        assert_eq!(
+            universe.transform_region(0, 0, 6, 2, Orientation::Rotate90),
+            Err(pattern::PatternError::TooLarge {
+                width: 2,
+                height: 6
+            })
+        );
+    }
+
+    #[test]
+    /// Translating wraps around a torus and drops cells
+    /// beyond the edges of a plane.
+    fn translates_regions() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.insert_rle(0, 0, "2o$2o!").unwrap();
+        universe.toggle_cell(5, 5);
+        universe.translate_region(0, 0, 2, 2, -1, -1).unwrap();
+        assert_eq!(
+            universe.get_cells()[universe.get_index(7, 7)],
+            cell::Cell::Alive
+        );
+        assert_eq!(
+            universe.get_cells()[universe.get_index(0, 0)],
+            cell::Cell::Alive
+        );
+        assert_eq!(
+            universe.get_cells()[universe.get_index(1, 1)],
+            cell::Cell::Dead
+        );
+        assert_eq!(universe.population(), 5);
+
+        universe.translate(1, 1);
+        assert_eq!(
+            universe.to_rle(),
+            "x = 7, y = 7, rule = B3/S23\n2o$2o5$6bo!\n"
+        );
+
+        universe.set_topology(topology::Topology::Plane).unwrap();
+        universe.translate(0, 5);
+        assert_eq!(universe.population(), 4);
+    }
+
+    #[test]
+    /// The merge modes combine pasted cells with the existing ones.
+    fn merges_cells() {
+        use cell::Cell::{Alive, Dead};
+        use pattern::MergeMode;
+
+        for (mode, expected) in [
+            (MergeMode::Or, [Dead, Alive, Alive, Alive]),
+            (MergeMode::Xor, [Dead, Alive, Alive, Dead]),
+            (MergeMode::Overwrite, [Dead, Alive, Dead, Alive]),
+        ] {
+            let merged: Vec<cell::Cell> =
+                [(Dead, Dead), (Dead, Alive), (Alive, Dead), (Alive, Alive)]
+                    .iter()
+                    .map(|&(existing, pasted)| mode.merge(existing, pasted))
+                    .collect();
+            assert_eq!(merged, expected);
+        }
+    }
+
+    #[test]
+    /// Regions can be cut, pasted in all merge modes, cleared and filled.
+    fn copies_and_pastes_regions() {
+        use pattern::MergeMode;
+
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 12, 12);
+        assert_eq!(
+            universe.paste(0, 0, MergeMode::Or),
+            Err(pattern::PatternError::EmptyClipboard)
+        );
+
+        universe.insert_rle(0, 0, "3o!").unwrap();
+        universe.cut_region(0, 0, 3, 1).unwrap();
+        assert_eq!(universe.population(), 0);
+        assert!(universe.has_clipboard());
+        assert_eq!(
+            (universe.clipboard_width(), universe.clipboard_height()),
+            (3, 1)
+        );
+
+        // The clipboard can be pasted over and over again:
+        universe.paste(5, 5, MergeMode::Or).unwrap();
+        universe.paste(5, 6, MergeMode::Or).unwrap();
+        assert_eq!(universe.to_rle(), "x = 4, y = 1, rule = B3/S23\n4o!\n");
+        universe.paste(5, 6, MergeMode::Xor).unwrap();
+        assert_eq!(universe.to_rle(), "x = 1, y = 1, rule = B3/S23\no!\n");
+
+        // Overwriting keeps the dead cells of the clipboard:
+        universe.fill_region(10, 10, 4, 4).unwrap();
+        assert_eq!(universe.population(), 17);
+        universe.copy_region(3, 3, 2, 2).unwrap();
+        universe.paste(10, 10, MergeMode::Overwrite).unwrap();
+        assert_eq!(universe.population(), 13);
+
+        universe.clear_region(9, 9, 6, 6).unwrap();
+        assert_eq!(universe.population(), 1);
+        assert_eq!(
+            universe.fill_region(0, 0, 13, 1),
+            Err(pattern::PatternError::TooLarge {
+                width: 13,
+                height: 1
+            })
+        );
+    }
+
+    #[test]
+    /// Edits and ticks can be undone and redone in any order.
+    fn undoes_and_redoes() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        // The undo history is turned off by default:
+        universe.toggle_cell(0, 0);
+        assert!(!universe.undo());
+        universe.toggle_cell(0, 0);
+
+        universe.set_undo_budget(1 << 20);
+        assert!(!universe.undo());
+
+        universe.toggle_cell(1, 1);
+        universe.insert_rle(3, 3, "3o!").unwrap();
+        let edited = universe.get_cells().clone();
+        universe.tick();
+        assert_eq!(universe.generation(), 1);
+
+        assert!(universe.undo());
+        assert_eq!(universe.get_cells(), &edited);
+        assert_eq!(universe.generation(), 0);
+        assert!(universe.undo());
+        assert_eq!(universe.population(), 1);
+        assert!(universe.undo());
+        assert_eq!(universe.population(), 0);
+        assert!(!universe.can_undo());
+
+        assert!(universe.redo());
+        assert!(universe.redo());
+        assert_eq!(universe.get_cells(), &edited);
+        assert!(universe.can_redo());
+
+        // A new edit forgets the undone tick:
+        universe.toggle_cell(6, 6);
+        assert!(!universe.can_redo());
+        assert!(!universe.redo());
+        assert!(universe.undo());
+        assert_eq!(universe.get_cells(), &edited);
+    }
+
+    #[test]
+    /// Changes to the rule are undone together with the cells
+    /// of the pattern that brought them.
+    fn undoes_rule_changes() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.set_undo_budget(1 << 20);
+        universe
+            .insert_rle(0, 0, "x = 2, y = 1, rule = B36/S23\n2o!")
+            .unwrap();
+        assert_eq!(universe.rule(), "B36/S23");
+        universe.set_rule("B3/S23/3").unwrap();
+        assert!(universe.undo());
+        assert_eq!(universe.rule(), "B36/S23");
+        assert!(universe.undo());
+        assert_eq!(universe.rule(), "B3/S23");
+        assert_eq!(universe.population(), 0);
+    }
+
+    #[test]
+    /// The undo history forgets the oldest steps when it runs out
+    /// of space and can be turned off.
+    fn limits_undo_history() {
+        let step_size = |changes: usize| {
+            undo::Step {
+                changes: vec![
+                    undo::CellChange {
+                        index: 0,
+                        before: (cell::Cell::Dead, 0),
+                        after: (cell::Cell::Alive, 0),
+                    };
+                    changes
+                ],
+                generations: (0, 0),
+                rules: None,
+                topologies: None,
+            }
+            .size()
+        };
+
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.set_undo_budget(2 * step_size(1) as u32);
+        for column in 0..3 {
+            universe.toggle_cell(0, column);
+        }
+        assert!(universe.undo());
+        assert!(universe.undo());
+        assert!(!universe.undo());
+        assert_eq!(universe.population(), 1);
+
+        // A step that's larger than the whole budget can't be undone:
+        universe.fill_region(4, 0, 8, 4).unwrap();
+        assert!(!universe.can_undo());
+        assert!(!universe.can_redo());
+
+        universe.set_undo_budget(0);
+        universe.toggle_cell(7, 7);
+        assert!(!universe.undo());
+        assert_eq!(universe.undo_budget(), 0);
+    }
+
+    #[test]
+    /// Seeking returns to earlier generations and forwards again.
+    fn seeks_generations() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 32, 32);
+        // The timeline is turned off by default:
+        universe.tick();
+        assert!(universe.seek(0).is_err());
+
+        universe = universe::Universe::new(universe::UniverseOption::Dead, 32, 32);
+        universe.set_undo_budget(1 << 20);
+        universe.set_timeline_budget(1 << 20);
+        universe.insert_rle(4, 4, "bo$2bo$3o!").unwrap();
+        let mut generations = vec![universe.get_cells().clone()];
+        for _ in 0..100 {
+            universe.tick();
+            generations.push(universe.get_cells().clone());
+        }
+
+        for &generation in [37, 0, 100, 64, 63, 65].iter() {
+            universe.seek(generation).unwrap();
+            assert_eq!(universe.generation(), generation);
+            assert_eq!(universe.get_cells(), &generations[generation as usize]);
+        }
+        assert_eq!(universe.earliest_generation(), 0);
+        assert_eq!(universe.latest_generation(), 100);
+        assert_eq!(
+            universe.seek(101),
+            Err(timeline::TimelineError::NotVisited { generation: 101 })
+        );
+
+        // A seek is undone like a single tick:
+        assert!(universe.undo());
+        assert_eq!(universe.generation(), 63);
+        assert_eq!(universe.get_cells(), &generations[63]);
+    }
+
+    #[test]
+    /// Edits forget the generations after them and keep the ones before.
+    fn edits_truncate_timeline() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 16, 16);
+        universe.set_timeline_budget(1 << 20);
+        universe.insert_rle(1, 1, "3o!").unwrap();
+        universe.tick_n(50);
+        universe.seek(20).unwrap();
+        universe.toggle_cell(10, 10);
+        assert_eq!(universe.latest_generation(), 20);
+        assert!(universe.seek(21).is_err());
+
+        let edited = universe.get_cells().clone();
+        universe.tick_n(5);
+        universe.seek(3).unwrap();
+        assert_eq!(universe.population(), 3);
+        universe.seek(20).unwrap();
+        assert_eq!(universe.get_cells(), &edited);
+        universe.seek(25).unwrap();
+        assert_eq!(universe.population(), 3);
+    }
+
+    #[test]
+    /// The keyframes are thinned out when they run out of space, but
+    /// all visited generations can still be recomputed.
+    fn thins_out_keyframes() {
+        let mut timeline = timeline::Timeline::new(2, 0);
+        let keyframe = |generation, edited| timeline::Keyframe {
+            generation,
+            cells: vec![cell::Cell::Dead; 16],
+            dying: None,
+            rule: rule::Rule::default(),
+            topology: topology::Topology::Torus,
+            edited,
+        };
+        timeline.set_budget(4 * keyframe(0, false).size());
+        timeline.record_edit(keyframe(0, true));
+        timeline.record(keyframe(2, false));
+        timeline.record(keyframe(4, false));
+        timeline.record(keyframe(6, false));
+        assert_eq!(timeline.interval(), 2);
+
+        timeline.record(keyframe(8, false));
+        assert_eq!(timeline.interval(), 4);
+        assert_eq!(timeline.keyframe_before(7).unwrap().generation, 4);
+        assert!(!timeline.wants(10));
+        assert!(timeline.wants(12));
+
+        // Edits can't be thinned out, so the oldest keyframes go:
+        for generation in 9..13 {
+            timeline.record_edit(keyframe(generation, true));
+        }
+        assert_eq!(timeline.earliest(), Some(9));
+        assert_eq!(timeline.latest(), Some(12));
+        assert!(timeline.keyframe_before(8).is_none());
+    }
+
+    #[test]
+    /// Resizing keeps the cells in place relative to the anchor.
+    fn resizes_universes() {
+        use universe::Anchor;
+
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 4, 4);
+        universe.insert_rle(1, 1, "2o$2o!").unwrap();
+        universe.set_age_tracking(true);
+        universe.tick_n(3);
+
+        universe.resize(8, 6, Anchor::Center).unwrap();
+        assert_eq!((universe.width(), universe.height()), (8, 6));
+        assert_eq!(universe.bounding_box(), Some((2, 3, 2, 2)));
+        assert_eq!(universe.get_ages().unwrap()[universe.get_index(2, 3)], 3);
+        assert_eq!(universe.get_ages().unwrap()[universe.get_index(0, 0)], 0);
+        assert_eq!(universe.get_dying_cells(), None);
+        assert!(!universe.can_undo());
+
+        // The resized universe keeps evolving as before:
+        universe.tick();
+        assert_eq!(universe.population(), 4);
+
+        universe.resize(5, 5, Anchor::BottomRight).unwrap();
+        assert_eq!(universe.bounding_box(), Some((1, 0, 2, 2)));
+        // Shrinking crops the cells beyond the new edges:
+        universe.resize(2, 2, Anchor::TopLeft).unwrap();
+        assert_eq!(universe.population(), 2);
+        universe.resize(4, 4, Anchor::BottomLeft).unwrap();
+        assert_eq!(universe.bounding_box(), Some((3, 0, 2, 1)));
+        assert_eq!(universe.earliest_generation(), universe.generation());
+
+        // Sizes whose number of cells overflows are rejected:
+        assert_eq!(
+            universe.resize(70_000, 70_000, Anchor::Center),
+            Err(pattern::PatternError::TooLarge {
+                width: 70_000,
+                height: 70_000
+            })
+        );
+        assert_eq!((universe.width(), universe.height()), (4, 4));
+    }
+
+    #[test]
+    /// A glider flies across the tiles of a sparse universe and only
+    /// leaves allocated tiles behind where it is.
+    fn sparse_glider_flies_on() {
+        let mut sparse = sparse::SparseUniverse::new();
+        sparse.insert_rle(1, 5, "bo$2bo$3o!").unwrap();
+        assert_eq!(sparse.population(), 5);
+        assert_eq!(sparse.tile_count(), 1);
+        assert_eq!(sparse.bounding_box(), Some((1, 5, 3, 3)));
+
+        // A glider moves one cell down and right every 4 generations:
+        sparse.tick_n(400);
+        assert_eq!(sparse.bounding_box(), Some((101, 105, 3, 3)));
+        assert!(sparse.tile_count() <= 4);
+        assert_eq!(
+            sparse.stats(),
+            universe::Stats {
+                generation: 400,
+                population: 5,
+                births: 2,
+                deaths: 2,
+            }
+        );
+    }
+
+    #[test]
+    /// The sparse universe evolves just like a large enough universe.
+    fn sparse_matches_universe() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 64, 64);
+        universe.set_topology(topology::Topology::Plane).unwrap();
+        universe.insert_rle(10, 40, "b2o$2o$bo!").unwrap();
+        let mut sparse = sparse::SparseUniverse::from_universe(&universe).unwrap();
+
+        for _ in 0..30 {
+            universe.tick();
+            sparse.tick();
+            assert_eq!(
+                sparse.viewport(0, 0, 64, 64).unwrap(),
+                universe
+                    .get_cells()
+                    .iter()
+                    .map(|&cell| cell as u8)
+                    .collect::<Vec<u8>>()
+            );
+            assert_eq!(sparse.population(), universe.population());
+        }
+    }
+
+    #[test]
+    /// Cells can be set anywhere and dead tiles are dropped.
+    fn sparse_cells_and_rules() {
+        let mut sparse = sparse::SparseUniverse::new();
+        sparse.set_cell(5, -1_000_000, true);
+        sparse.set_cell(5, -1_000_000, true);
+        assert!(sparse.is_alive(5, -1_000_000));
+        assert!(!sparse.is_alive(-1_000_000, 5));
+        assert_eq!(sparse.population(), 1);
+        assert_eq!(
+            sparse.viewport(4, -1_000_001, 3, 2),
+            Ok(vec![0, 0, 0, 0, 1, 0])
+        );
+        assert_eq!(
+            sparse.viewport(0, 0, 1 << 16, 1 << 16),
+            Err(pattern::PatternError::TooLarge {
+                width: 1 << 16,
+                height: 1 << 16
+            })
+        );
+
+        sparse.toggle_cell(5, -1_000_000);
+        assert_eq!(sparse.population(), 0);
+        assert_eq!(sparse.tile_count(), 0);
+        assert_eq!(sparse.bounding_box(), None);
+
+        assert_eq!(
+            sparse.set_rule("B03/S23"),
+            Err(rule::RuleError::Unsupported("B03/S23".to_string()))
+        );
+        sparse.set_rule("B36/S23").unwrap();
+        assert_eq!(sparse.rule(), "B36/S23");
+    }
+
+    #[test]
+    /// Oversized and overflowing RLE patterns are rejected
+    /// before any cells are allocated.
+    fn rejects_oversized_rle() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        assert_eq!(
+            universe.insert_rle(0, 0, "4294967295o2o!"),
+            Err(pattern::PatternError::TooLarge {
+                width: 4294967295,
+                height: 1
+            })
+        );
+        assert_eq!(
+            rle::parse("4294967295o2o!"),
+            Err(pattern::PatternError::InvalidRunCount { line: 1 })
+        );
+        assert_eq!(
+            rle::parse("4294967295$o!"),
+            Err(pattern::PatternError::InvalidRunCount { line: 1 })
+        );
+        assert_eq!(
+            universe.insert_rle(0, 0, "300000000o!"),
+            Err(pattern::PatternError::TooLarge {
+                width: 300000000,
+                height: 1
+            })
+        );
+        assert_eq!(
+            universe.insert_rle(0, 0, "x = 70000, y = 70000\no!"),
+            Err(pattern::PatternError::TooLarge {
+                width: 70000,
+                height: 70000
+            })
+        );
+        assert_eq!(
+            rle::parse("x = 70000, y = 70000\no!"),
+            Err(pattern::PatternError::TooLarge {
+                width: 70000,
+                height: 70000
+            })
+        );
+        assert_eq!(universe.population(), 0);
+    }
+
+    #[test]
+    /// Undoing a seek across a change of the topology brings
+    /// back the topology the seek started from.
+    fn undoes_seeks_across_topologies() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 16, 16);
+        universe.set_undo_budget(1 << 20);
+        universe.set_timeline_budget(1 << 20);
+        universe.insert_rle(0, 0, "bo$2bo$3o!").unwrap();
+        universe.tick_n(10);
+        universe.set_topology(topology::Topology::Plane).unwrap();
+        universe.tick_n(5);
+
+        universe.seek(3).unwrap();
+        assert_eq!(universe.topology(), topology::Topology::Torus);
+        assert!(universe.undo());
+        assert_eq!(universe.generation(), 15);
+        assert_eq!(universe.topology(), topology::Topology::Plane);
+        assert!(universe.redo());
+        assert_eq!(universe.topology(), topology::Topology::Torus);
+    }
+
+    #[test]
+    /// Ranges above the cap and counts far beyond the neighbourhood
+    /// are rejected before anything is allocated or inserted.
+    fn rejects_huge_hrot_rules() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        assert_eq!(
+            universe.set_rule("R70000,C0,S1,B1,NM"),
+            Err(rule::RuleError::InvalidRange(70000))
+        );
+        assert_eq!(
+            universe.set_rule("R1,C0,S2-3,B3-4000000000"),
+            Err(rule::RuleError::CountOutOfRange(4000000000))
+        );
+        assert_eq!(
+            universe.set_neighborhood(neighborhood::NeighborhoodKind::Moore, 501),
+            Err(rule::RuleError::InvalidRange(501))
+        );
+        assert!(universe.set_rule("R500,C0,S1,B1,NM").is_ok());
+    }
+
+    #[test]
+    /// The lookup tables of a rule decide every cell in
+    /// the same way as the rule itself.
+    fn looks_up_transitions() {
+        for rulestring in &[
+            "B3/S23",
+            "B2/S/C3",
+            "R5,C0,M1,S34..58,B34..45,NM",
+            "R2,C4,S6-9,B7-8,NN",
+        ] {
+            let rule: rule::Rule = rulestring.parse().unwrap();
+            let transitions = rule.transitions();
+            for live_neighbors in 0..=rule.neighborhood().size() {
+                for &cell in &[cell::Cell::Dead, cell::Cell::Alive] {
+                    for dying in 0..rule.states() - 1 {
+                        assert_eq!(
+                            transitions.next_state(cell, dying, live_neighbors),
+                            rule.next_state(cell, dying, live_neighbors)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    /// A history without capacity doesn't detect anything, and
+    /// the state hash tells the phases of a blinker apart.
+    fn skips_hashing_without_history() {
+        use cycle::Stability;
+
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.insert_rle(2, 2, "3o!").unwrap();
+        let horizontal = universe.state_hash();
+        universe.tick();
+        assert_ne!(universe.state_hash(), horizontal);
+        universe.tick();
+        assert_eq!(universe.state_hash(), horizontal);
+
+        universe.set_history_capacity(0);
+        universe.tick_n(4);
+        assert_eq!(universe.stability(), Stability::Evolving);
+        universe.set_history_capacity(4);
+        universe.tick_n(2);
+        assert_eq!(universe.stability(), Stability::Oscillating);
+    }
+
+    #[test]
+    /// Life 1.06 patterns with huge coordinate spans are rejected
+    /// before their cells are allocated.
+    fn rejects_oversized_life106() {
+        assert_eq!(
+            life106::parse("#Life 1.06\n-2147483648 -2147483648\n2147483647 2147483647\n"),
+            Err(pattern::PatternError::TooLarge {
+                width: u32::MAX,
+                height: u32::MAX
+            })
+        );
+        assert_eq!(
+            life106::parse("#Life 1.06\n0 0\n10000 10000\n"),
+            Err(pattern::PatternError::TooLarge {
+                width: 10001,
+                height: 10001
+            })
+        );
+        assert_eq!(
+            pattern::Pattern::from_coordinates(&[(0, u32::MAX)]),
+            Err(pattern::PatternError::TooLarge {
+                width: u32::MAX,
+                height: 1
+            })
+        );
+
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 16, 16);
+        assert_eq!(
+            universe.insert_life106(0, 0, "#Life 1.06\n0 0\n0 16\n"),
+            Err(pattern::PatternError::TooLarge {
+                width: 1,
+                height: 17
+            })
+        );
+        assert_eq!(universe.population(), 0);
+    }
+
+    #[test]
+    /// Universes with two-state rules don't keep dying stages, and
+    /// the dying cells of Generations rules are written as multi-state
+    /// RLE.
+    fn writes_dying_cells_as_states() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        assert_eq!(universe.get_dying_cells(), None);
+        assert!(universe.dying_cells().is_null());
+
+        universe.set_undo_budget(1 << 20);
+        universe.set_rule("345/2/4").unwrap();
+        universe.insert_rle(2, 2, "o!").unwrap();
+        assert_eq!(universe.to_rle(), "x = 1, y = 1, rule = B2/S345/C4\nA!\n");
+        universe.tick();
+        assert_eq!(universe.to_rle(), "x = 1, y = 1, rule = B2/S345/C4\nB!\n");
+        universe.toggle_cell(3, 3);
+        assert_eq!(
+            universe.to_rle(),
+            "x = 2, y = 2, rule = B2/S345/C4\nB$.A!\n"
+        );
+
+        // Dropping the dying states can be undone:
+        universe.set_rule("B3/S23").unwrap();
+        assert_eq!(universe.get_dying_cells(), None);
+        assert_eq!(universe.to_rle(), "x = 1, y = 1, rule = B3/S23\no!\n");
+        assert!(universe.undo());
+        assert_eq!(
+            universe.get_dying_cells().unwrap()[universe.get_index(2, 2)],
+            1
+        );
+
+        let glider = rle::parse("bo$2bo$3o!").unwrap();
+        let states = [0, 1, 0, 0, 0, 25, 200, 2, 0];
+        assert_eq!(
+            rle::write_states(&glider, &states),
+            "x = 3, y = 3, rule = B3/S23\n.A$2.pA$wHB!\n"
+        );
+    }
+
+    #[test]
+    /// Hexagonal rules need an even number of rows on topologies that
+    /// wrap around from the last row to the first one.
+    fn rejects_hexagonal_rules_on_odd_heights() {
+        use neighborhood::NeighborhoodKind;
+        use topology::Topology;
+
+        let unsupported = || Err(rule::RuleError::Unsupported("B2/S34H".to_string()));
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 7);
+        assert_eq!(universe.set_rule("B2/S34H"), unsupported());
+        universe.set_rule("B2/S34").unwrap();
+        assert_eq!(
+            universe.set_neighborhood(NeighborhoodKind::Hexagonal, 1),
+            unsupported()
+        );
+        assert_eq!(universe.rule(), "B2/S34");
+
+        // Rows that don't wrap around can't be shifted the wrong way:
+        universe.set_topology(Topology::HorizontalCylinder).unwrap();
+        universe.set_rule("B2/S34H").unwrap();
+        assert_eq!(universe.set_topology(Topology::KleinBottle), unsupported());
+        assert_eq!(universe.topology(), Topology::HorizontalCylinder);
+        universe.set_topology(Topology::Plane).unwrap();
+
+        universe.resize(8, 8, universe::Anchor::TopLeft).unwrap();
+        universe.set_topology(Topology::Torus).unwrap();
+        assert_eq!(
+            universe.resize(8, 9, universe::Anchor::TopLeft),
+            unsupported().map_err(pattern::PatternError::InvalidRule)
+        );
+        assert_eq!(universe.height(), 8);
+
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 7);
+        assert_eq!(
+            universe.insert_rle(0, 0, "x = 1, y = 1, rule = B2/S34H\no!"),
+            Err(pattern::PatternError::InvalidRule(
+                rule::RuleError::Unsupported("B2/S34H".to_string())
+            ))
+        );
+        assert_eq!(universe.population(), 0);
+    }
+
+    #[test]
+    /// Plaintext patterns that are too large are rejected
+    /// instead of being allocated.
+    fn rejects_oversized_plaintext() {
+        let mut plaintext = "O".repeat(70_000);
+        plaintext.push_str(&"\n.".repeat(1_000));
+        assert_eq!(
+            plaintext::parse(&plaintext),
+            Err(pattern::PatternError::TooLarge {
+                width: 70_000,
+                height: 1_001
+            })
+        );
+
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 4, 4);
+        assert_eq!(
+            universe.insert_plaintext(0, 0, "OOOOO"),
+            Err(pattern::PatternError::TooLarge {
+                width: 5,
+                height: 1
+            })
+        );
+        assert_eq!(
+            universe.insert_plaintext(0, 0, "O\nO\nO\nO\nO\n\n"),
+            Err(pattern::PatternError::TooLarge {
+                width: 1,
+                height: 5
+            })
+        );
+        assert_eq!(universe.population(), 0);
+    }
+
+    #[test]
+    /// Setting many cells at once is a single edit.
+    fn sets_cells_in_one_edit() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.set_undo_budget(1 << 20);
+        universe.set_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        assert_eq!(universe.population(), 5);
+
+        assert!(universe.undo());
+        assert_eq!(universe.population(), 0);
+        assert!(!universe.can_undo());
+    }
+
+    #[test]
+    /// Multi-state patterns that are exported can be imported again,
+    /// together with their dying stages.
+    fn imports_multi_state_rle() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.set_rule("/2/3").unwrap();
+        universe.insert_rle(1, 1, "2o!").unwrap();
+        universe.tick();
+        let exported = universe.to_rle();
+        assert_eq!(exported, "x = 2, y = 3, rule = B2/S/C3\n2A$2B$2A!\n");
+
+        let mut imported = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        imported.insert_rle(1, 1, &exported).unwrap();
+        assert_eq!(imported.to_rle(), exported);
+        assert_eq!(imported.population(), 4);
+        assert_eq!(
+            imported.get_dying_cells().unwrap()[imported.get_index(2, 1)],
+            1
+        );
+
+        let (glider, states) =
+            rle::parse_states_within("x = 3, y = 3\n.A$2.pA$wHB!", 3, 3).unwrap();
+        assert_eq!(states, [0, 1, 0, 0, 0, 25, 200, 2, 0]);
+        assert_eq!(glider.live_cells().count(), 1);
+        assert_eq!(
+            rle::parse("yX!"),
+            Err(pattern::PatternError::UnexpectedCharacter {
+                character: 'X',
+                line: 1
+            })
+        );
+        assert_eq!(
+            rle::parse("2pb!"),
+            Err(pattern::PatternError::UnexpectedCharacter {
+                character: 'b',
+                line: 1
+            })
+        );
+    }
+
+    #[test]
+    /// Changes to the topology can be undone and redone like edits.
+    fn undoes_topology_changes() {
+        use topology::Topology;
+
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 8, 8);
+        universe.set_undo_budget(1 << 20);
+        universe.insert_rle(0, 0, "3o!").unwrap();
+        universe.set_topology(Topology::Plane).unwrap();
+        universe.tick();
+        assert_eq!(universe.population(), 2);
+
+        assert!(universe.undo());
+        assert!(universe.undo());
+        assert_eq!(universe.topology(), Topology::Torus);
+        assert_eq!(universe.population(), 3);
+
+        assert!(universe.redo());
+        assert_eq!(universe.topology(), Topology::Plane);
+        // Setting the same topology again isn't a step of its own:
+        universe.set_topology(Topology::Plane).unwrap();
+        assert!(universe.undo());
+        assert_eq!(universe.topology(), Topology::Torus);
+    }
+
+    #[test]
+    /// Objects that are two cells apart, like the blocks of a bi-block,
+    /// are counted as one pseudo-object, and dying cells belong to the
+    /// objects they trail behind.
+    fn takes_census_of_pseudo_objects_and_dying_cells() {
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 32, 32);
+        universe.insert_rle(4, 4, "2ob2o$2ob2o!").unwrap();
+        universe.insert_rle(20, 4, "2o3b2o$2o3b2o!").unwrap();
+        let census = universe.census();
+        assert_eq!(census.count("xs8_rr"), 1);
+        assert_eq!(census.count("xs4_33"), 2);
+        assert_eq!(census.total(), 3);
+
+        // The spaceship of Brian's Brain has two dying cells behind its
+        // two living ones, without which it would explode:
+        let mut universe = universe::Universe::new(universe::UniverseOption::Dead, 32, 32);
+        universe.set_rule("/2/3").unwrap();
+        universe.insert_rle(4, 4, "2A$2B!").unwrap();
+        universe.insert_rle(20, 20, "2B$2A!").unwrap();
+        universe.insert_rle(12, 12, "2A!").unwrap();
+        let census = universe.census();
+        assert_eq!(census.count("xq1_3"), 2);
+        assert_eq!(census.count(census::PATHOLOGICAL), 1);
+    }
 }
@@ -0,0 +1,58 @@
+//! A library of well-known patterns that are built into the crate.
+//!
+//! The patterns are stored in the RLE format and parsed on demand.
+//! They don't carry a rule, so inserting one into a `Universe` keeps
+//! whatever rule it runs on.
+use wasm_bindgen::prelude::*;
+
+use super::pattern::{Pattern, PatternError};
+
+/// The names of the built-in patterns and their cells in the RLE format.
+const BUILTINS: [(&str, &str); 10] = [
+    ("glider", "x = 3, y = 3\n2bo$obo$b2o!"),
+    ("lwss", "x = 5, y = 4\nbo2bo$o4b$o3bo$4o!"),
+    ("mwss", "x = 6, y = 5\n3bo2b$bo3bo$o5b$o4bo$5o!"),
+    ("hwss", "x = 7, y = 5\n3b2o2b$bo4bo$o6b$o5bo$6o!"),
+    (
+        "pulsar",
+        "x = 13, y = 13\n2b3o3b3o2b2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2b2$\
+         2b3o3b3o2b$o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+    ),
+    ("pentadecathlon", "x = 10, y = 3\n2bo4bo$2ob4ob2o$2bo4bo!"),
+    (
+        "gosper-glider-gun",
+        "x = 36, y = 9\n24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$\
+         2o8bo5bo3b2o$2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!",
+    ),
+    ("r-pentomino", "x = 3, y = 3\nb2o$2o$bo!"),
+    ("acorn", "x = 7, y = 3\nbo$3bo$2o2b3o!"),
+    ("diehard", "x = 8, y = 3\n6bo$2o$bo3b3o!"),
+];
+
+/// Returns the names of all built-in patterns.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    BUILTINS.iter().map(|&(name, _)| name)
+}
+
+/// Returns the built-in pattern with the given `name`,
+/// which is looked up regardless of its case.
+///
+/// # Errors
+/// Names that aren't in the library are rejected
+/// with `PatternError::UnknownPattern`.
+pub fn get(name: &str) -> Result<Pattern, PatternError> {
+    let (name, rle) = BUILTINS
+        .iter()
+        .find(|(builtin, _)| builtin.eq_ignore_ascii_case(name))
+        .ok_or_else(|| PatternError::UnknownPattern(name.to_string()))?;
+
+    let mut pattern = super::rle::parse(rle)?;
+    pattern.set_name(Some(name.to_string()));
+    Ok(pattern)
+}
+
+/// Returns the names of all built-in patterns to JavaScript.
+#[wasm_bindgen]
+pub fn builtin_patterns() -> Vec<String> {
+    names().map(String::from).collect()
+}
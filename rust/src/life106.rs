@@ -0,0 +1,129 @@
+//! Support for the Life 1.06 pattern format.
+//!
+//! A Life 1.06 file starts with the `#Life 1.06` header, followed by
+//! one line per living cell with its `x` (column) and `y` (row)
+//! coordinate, separated by whitespace. Coordinates may be negative.
+
+use super::pattern::{Pattern, PatternError};
+
+/// The header every Life 1.06 file starts with.
+const HEADER: &str = "#Life 1.06";
+
+/// Parses a `Pattern` from a string in the Life 1.06 format.
+///
+/// The coordinates are shifted so that the topmost and leftmost
+/// living cells end up in the first row and column of the `Pattern`.
+/// Like in RLE, `#N` lines set the name and `#C` or `#D` lines are
+/// kept as comments.
+pub fn parse(life106: &str) -> Result<Pattern, PatternError> {
+    parse_within(life106, u32::MAX, u32::MAX)
+}
+
+/// Parses a `Pattern` from a string in the Life 1.06 format, whose
+/// cells may span at most `max_width` columns and `max_height` rows.
+///
+/// The span of the coordinates is checked before the `Pattern` is
+/// built, so a pattern that couldn't be inserted anyway is never built.
+///
+/// # Errors
+/// Patterns that are larger are rejected with `PatternError::TooLarge`,
+/// as are patterns with more than `MAX_CELLS` cells.
+pub fn parse_within(
+    life106: &str,
+    max_width: u32,
+    max_height: u32,
+) -> Result<Pattern, PatternError> {
+    let mut lines = life106.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.trim() == HEADER => {}
+        _ => return Err(PatternError::InvalidHeader { line: 1 }),
+    }
+
+    let mut name = None;
+    let mut comments = Vec::new();
+    let mut coordinates: Vec<(i32, i32)> = Vec::new();
+
+    for (idx, line) in lines {
+        // Line numbers start at 1 for our error messages:
+        let line_number = idx + 1;
+        let line = line.trim();
+
+        if let Some(comment) = line.strip_prefix('#') {
+            let mut chars = comment.chars();
+            match chars.next() {
+                Some('N') => name = Some(chars.as_str().trim().to_string()),
+                Some('C') | Some('D') => comments.push(chars.as_str().trim().to_string()),
+                _ => {}
+            }
+            continue;
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let invalid = PatternError::InvalidCoordinate { line: line_number };
+        let mut values = line.split_whitespace().map(|value| value.parse::<i32>());
+        match (values.next(), values.next(), values.next()) {
+            (Some(Ok(x)), Some(Ok(y)), None) => coordinates.push((y, x)),
+            _ => return Err(invalid),
+        }
+    }
+
+    let top = coordinates.iter().map(|&(row, _)| row).min().unwrap_or(0);
+    let left = coordinates
+        .iter()
+        .map(|&(_, column)| column)
+        .min()
+        .unwrap_or(0);
+    let bottom = coordinates.iter().map(|&(row, _)| row).max().unwrap_or(0);
+    let right = coordinates
+        .iter()
+        .map(|&(_, column)| column)
+        .max()
+        .unwrap_or(0);
+
+    // The size is one more than the span of the coordinates,
+    // which needs an `u64` at the ends of the `i32` range:
+    if !coordinates.is_empty() {
+        let height = (bottom as i64 - top as i64 + 1) as u64;
+        let width = (right as i64 - left as i64 + 1) as u64;
+        if width > max_width as u64 || height > max_height as u64 {
+            return Err(PatternError::TooLarge {
+                width: width.min(u32::MAX as u64) as u32,
+                height: height.min(u32::MAX as u64) as u32,
+            });
+        }
+    }
+    // The difference of two `i32` values always fits into an `u32`:
+    let shifted: Vec<(u32, u32)> = coordinates
+        .iter()
+        .map(|&(row, column)| {
+            (
+                (row as i64 - top as i64) as u32,
+                (column as i64 - left as i64) as u32,
+            )
+        })
+        .collect();
+
+    let mut pattern = Pattern::from_coordinates(&shifted)?;
+    pattern.set_name(name);
+    for comment in comments {
+        pattern.add_comment(comment);
+    }
+
+    Ok(pattern)
+}
+
+/// Writes a `Pattern` as a string in the Life 1.06 format.
+///
+/// The top left corner of the `Pattern` is the origin of the
+/// coordinates. The format only knows about living cells, so the
+/// name, comments and `Rule` of the `Pattern` are left out.
+pub fn write(pattern: &Pattern) -> String {
+    let mut life106 = format!("{}\n", HEADER);
+    for (row, column) in pattern.live_cells() {
+        life106.push_str(&format!("{} {}\n", column, row));
+    }
+    life106
+}
@@ -0,0 +1,143 @@
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// The shape of the cells that count as neighbours of a cell.
+pub enum NeighborhoodKind {
+    /// All cells within a square around the cell. With a range of 1
+    /// these are the 8 cells of Conway's Game of Life.
+    Moore,
+    /// All cells within a diamond around the cell, i.e. the ones that
+    /// can be reached in `range` orthogonal steps. With a range of 1
+    /// these are the 4 orthogonally adjacent cells.
+    VonNeumann,
+    /// All cells within a hexagon around the cell, on a grid in which
+    /// every odd row is shifted to the right by half a cell. With a
+    /// range of 1 these are the 6 cells that share an edge with it.
+    /// If the last row wraps around to the first one, the number of
+    /// rows has to be even, see `Topology::wraps_vertically`.
+    Hexagonal,
+}
+
+/// The largest range of a `Neighborhood`, the same limit as in Golly.
+///
+/// The number of cells grows with the square of the range, so larger
+/// neighbourhoods couldn't be counted in any reasonable time anyway.
+pub const MAX_RANGE: u32 = 500;
+
+/// A `Neighborhood` is a `NeighborhoodKind` together with its range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Neighborhood {
+    kind: NeighborhoodKind,
+    range: u32,
+}
+
+impl Neighborhood {
+    /// Creates a `Neighborhood` of the given `kind` and `range`.
+    ///
+    /// # Panics
+    /// The method panics if the `range` is 0, as such a neighbourhood
+    /// wouldn't contain any cells, or larger than `MAX_RANGE`.
+    pub fn new(kind: NeighborhoodKind, range: u32) -> Neighborhood {
+        assert!(range > 0, "a neighborhood needs a range of at least 1");
+        assert!(
+            range <= MAX_RANGE,
+            "a neighborhood can't have a range above {}",
+            MAX_RANGE
+        );
+        Neighborhood { kind, range }
+    }
+
+    /// Returns the `NeighborhoodKind` of the `Neighborhood`.
+    pub fn kind(&self) -> NeighborhoodKind {
+        self.kind
+    }
+
+    /// Returns the range of the `Neighborhood`.
+    pub fn range(&self) -> u32 {
+        self.range
+    }
+
+    /// Returns the number of cells in the `Neighborhood`,
+    /// not counting the cell in its center.
+    pub fn size(&self) -> u32 {
+        let range = self.range;
+        // The range is capped by `MAX_RANGE`, so none of these overflow,
+        // but a wrong count would be far worse than a panic:
+        let size = match self.kind {
+            NeighborhoodKind::Moore => (2 * range + 1)
+                .checked_mul(2 * range + 1)
+                .map(|side| side - 1),
+            NeighborhoodKind::VonNeumann => (2 * range).checked_mul(range + 1),
+            NeighborhoodKind::Hexagonal => (3 * range).checked_mul(range + 1),
+        };
+        size.expect("the neighborhood has too many cells")
+    }
+
+    /// Returns `true` for the 8 cell neighbourhood of Life-like rules.
+    pub fn is_life_like(&self) -> bool {
+        self.kind == NeighborhoodKind::Moore && self.range == 1
+    }
+
+    /// Returns the `(row, column)` offsets of all cells in the
+    /// `Neighborhood` of a cell in the given `row`.
+    ///
+    /// Only hexagonal neighbourhoods depend on the row, as the shift of
+    /// the odd rows changes which columns of the rows above and below
+    /// are adjacent.
+    ///
+    /// # Algorithm explanation
+    ///
+    /// For hexagonal neighbourhoods, we convert the offset coordinates
+    /// into cube coordinates (q, r, s) with q + r + s = 0, in which the
+    /// distance between two cells is the largest of the differences of
+    /// their coordinates. We collect all cells within the range and
+    /// convert them back.
+    pub fn offsets(&self, row: u32) -> Vec<(i64, i64)> {
+        let range = self.range as i64;
+        let mut offsets = Vec::with_capacity(self.size() as usize);
+
+        match self.kind {
+            NeighborhoodKind::Moore | NeighborhoodKind::VonNeumann => {
+                for delta_row in -range..=range {
+                    for delta_column in -range..=range {
+                        let is_center = delta_row == 0 && delta_column == 0;
+                        let is_inside = self.kind == NeighborhoodKind::Moore
+                            || delta_row.abs() + delta_column.abs() <= range;
+                        if !is_center && is_inside {
+                            offsets.push((delta_row, delta_column));
+                        }
+                    }
+                }
+            }
+            NeighborhoodKind::Hexagonal => {
+                // The shift of the current row, which is 1 for odd rows:
+                let parity = (row % 2) as i64;
+                for delta_r in -range..=range {
+                    // All cube coordinates within the range satisfy
+                    // |q| <= range, |r| <= range and |q + r| <= range:
+                    for delta_q in (-range).max(-delta_r - range)..=range.min(range - delta_r) {
+                        if delta_q == 0 && delta_r == 0 {
+                            continue;
+                        }
+                        // Converting the cube coordinates back into offset
+                        // coordinates depends on the shift of the target row.
+                        // The shifted row below or above is `parity + delta_r`:
+                        let shifted = parity + delta_r;
+                        let delta_column = delta_q + (shifted - (shifted & 1)) / 2;
+                        offsets.push((delta_r, delta_column));
+                    }
+                }
+            }
+        }
+
+        offsets
+    }
+}
+
+/// The Moore neighbourhood of range 1 is the default `Neighborhood`.
+impl Default for Neighborhood {
+    fn default() -> Neighborhood {
+        Neighborhood::new(NeighborhoodKind::Moore, 1)
+    }
+}
@@ -0,0 +1,304 @@
+use wasm_bindgen::prelude::*;
+
+use super::cell::Cell;
+use super::rule::{Rule, RuleError};
+use super::topology::Topology;
+use super::universe::{Universe, UniverseOption};
+
+/// The number of cells that are stored in one word.
+const BITS: u32 = u64::BITS;
+
+#[wasm_bindgen]
+/// A `PackedUniverse` stores its cells as single bits.
+///
+/// Compared to the `Universe`, which spends one byte on every `Cell`,
+/// the bit-packed storage needs only an eighth of the memory. Every row
+/// starts with a new `u64` word and bit `n` of a row belongs to column
+/// `n`. As WebAssembly is little-endian, the cell in `row` and `column`
+/// can be found in byte `row * bytes_per_row + column / 8` at bit
+/// `column % 8` of the buffer returned by `cells()`.
+///
+/// The neighbours are counted for 64 cells at once by adding up
+/// the shifted words of the surrounding rows bit by bit.
+pub struct PackedUniverse {
+    width: u32,
+    height: u32,
+    words_per_row: u32,
+    words: Vec<u64>,
+    // Just like the `Universe`, we write each generation
+    // into a second buffer and swap both afterwards:
+    next: Vec<u64>,
+    rule: Rule,
+}
+
+// These functions won't be exposed to JavaScript as
+// they're only meant to handle the internal state:
+impl PackedUniverse {
+    /// Returns the index of the word that holds the cell in
+    /// `row` and `column` and the position of its bit.
+    fn get_index(&self, row: u32, column: u32) -> (usize, u32) {
+        (
+            (row * self.words_per_row + column / BITS) as usize,
+            column % BITS,
+        )
+    }
+
+    /// Returns the mask of the bits in a word of a row that
+    /// belong to actual cells, as the last word of each row
+    /// is only partially filled.
+    fn valid_bits(&self, word: u32) -> u64 {
+        let remaining = self.width - word * BITS;
+        if remaining >= BITS {
+            u64::MAX
+        } else {
+            (1 << remaining) - 1
+        }
+    }
+
+    /// Returns the words of a row shifted by one column, so that
+    /// each bit holds the state of its western (`west`) and eastern
+    /// (`east`) neighbour. The row wraps around at both ends.
+    fn shifted(&self, row: &[u64], word: usize) -> (u64, u64) {
+        let last = row.len() - 1;
+        // The position of the last cell in the last word of a row:
+        let last_bit = (self.width - 1) % BITS;
+        let first_cell = row[0] & 1;
+        let last_cell = (row[last] >> last_bit) & 1;
+
+        let previous = if word == 0 {
+            last_cell
+        } else {
+            row[word - 1] >> (BITS - 1)
+        };
+        let west = (row[word] << 1) | previous;
+
+        let east = if word == last {
+            (row[word] >> 1) | (first_cell << last_bit)
+        } else {
+            (row[word] >> 1) | (row[word + 1] << (BITS - 1))
+        };
+
+        (west, east)
+    }
+
+    /// Returns the mask of bits that satisfy the `Rule` for
+    /// the given counts of living neighbours.
+    ///
+    /// The counts are stored bit-sliced: `counts[i]` holds
+    /// bit `i` of the count of each of the 64 cells.
+    fn matching(counts: &[u64; 4], matches: impl Fn(u32) -> bool) -> u64 {
+        (0..=8u32)
+            .filter(|&count| matches(count))
+            .fold(0, |mask, count| {
+                mask | (0..4).fold(u64::MAX, |equal, i| {
+                    if count & (1 << i) != 0 {
+                        equal & counts[i]
+                    } else {
+                        equal & !counts[i]
+                    }
+                })
+            })
+    }
+
+    /// Returns the `Cell` in the given `row` and `column`.
+    pub fn get_cell(&self, row: u32, column: u32) -> Cell {
+        let (idx, bit) = self.get_index(row, column);
+        if self.words[idx] & (1 << bit) != 0 {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+
+    /// Sets the `Cell` in the given `row` and `column`.
+    pub fn set_cell(&mut self, row: u32, column: u32, cell: Cell) {
+        let (idx, bit) = self.get_index(row, column);
+        match cell {
+            Cell::Alive => self.words[idx] |= 1 << bit,
+            Cell::Dead => self.words[idx] &= !(1 << bit),
+        }
+    }
+}
+
+// These methods for `PackedUniverse` will be
+// exposed to the JavaScript API:
+#[wasm_bindgen]
+impl PackedUniverse {
+    /// Creates a `PackedUniverse` of only dead cells
+    /// that evolves by Conway's Game of Life.
+    pub fn new(width: u32, height: u32) -> PackedUniverse {
+        super::utils::set_panic_hook();
+
+        let words_per_row = width.div_ceil(BITS);
+        PackedUniverse {
+            width,
+            height,
+            words_per_row,
+            words: vec![0; (words_per_row * height) as usize],
+            next: vec![0; (words_per_row * height) as usize],
+            rule: Rule::default(),
+        }
+    }
+
+    /// Creates a `PackedUniverse` with the same cells
+    /// and `Rule` as the given `Universe`.
+    ///
+    /// # Errors
+    /// The bit-parallel counting only covers the 8 cell neighbourhood
+    /// and a single bit per cell, so rules with any other neighbourhood
+    /// or with dying states are rejected with `RuleError::Unsupported`.
+    /// The rows and words always wrap around, so the same goes for a
+    /// `Universe` with another `Topology` than `Topology::Torus`.
+    pub fn from_universe(universe: &Universe) -> Result<PackedUniverse, RuleError> {
+        if universe.topology() != Topology::Torus {
+            return Err(RuleError::Unsupported(universe.rule()));
+        }
+        let mut packed = PackedUniverse::new(universe.width(), universe.height());
+        packed.set_rule(&universe.rule())?;
+        for row in 0..universe.height() {
+            for column in 0..universe.width() {
+                let cell = universe.get_cells()[universe.get_index(row, column)];
+                packed.set_cell(row, column, cell);
+            }
+        }
+        Ok(packed)
+    }
+
+    /// Returns an `Universe` with the same cells and `Rule`.
+    pub fn to_universe(&self) -> Universe {
+        let mut universe = Universe::new(UniverseOption::Dead, self.width, self.height);
+        let living: Vec<(u32, u32)> = (0..self.height)
+            .flat_map(|row| (0..self.width).map(move |column| (row, column)))
+            .filter(|&(row, column)| self.get_cell(row, column) == Cell::Alive)
+            .collect();
+        universe.set_cells(&living);
+        // The rule of a `PackedUniverse` is always a valid rulestring:
+        universe.set_rule(&self.rule.to_string()).unwrap();
+        universe
+    }
+
+    /// Advances the time t one tick in time (= delta t).
+    ///
+    /// For each word of a row we add up the eight words that hold the
+    /// neighbours of its cells: the shifted and unshifted words of the
+    /// rows above and below and the shifted words of the row itself.
+    pub fn tick(&mut self) {
+        let mut next = std::mem::take(&mut self.next);
+        let words_per_row = self.words_per_row as usize;
+
+        for row in 0..self.height as usize {
+            let north = if row == 0 {
+                self.height as usize - 1
+            } else {
+                row - 1
+            };
+            let south = if row == self.height as usize - 1 {
+                0
+            } else {
+                row + 1
+            };
+
+            let row_words =
+                |row: usize| &self.words[row * words_per_row..(row + 1) * words_per_row];
+            let (north_words, center_words, south_words) =
+                (row_words(north), row_words(row), row_words(south));
+
+            for word in 0..words_per_row {
+                let (north_west, north_east) = self.shifted(north_words, word);
+                let (west, east) = self.shifted(center_words, word);
+                let (south_west, south_east) = self.shifted(south_words, word);
+                let neighbors = [
+                    north_west,
+                    north_words[word],
+                    north_east,
+                    west,
+                    east,
+                    south_west,
+                    south_words[word],
+                    south_east,
+                ];
+
+                // Add up the neighbours with a chain of half adders:
+                let mut counts = [0u64; 4];
+                for neighbor in neighbors {
+                    let mut carry = neighbor;
+                    for count in counts.iter_mut() {
+                        let overflow = *count & carry;
+                        *count ^= carry;
+                        carry = overflow;
+                    }
+                }
+
+                let alive = center_words[word];
+                let born = !alive & Self::matching(&counts, |count| self.rule.is_birth(count));
+                let survived =
+                    alive & Self::matching(&counts, |count| self.rule.is_survival(count));
+
+                next[row * words_per_row + word] = (born | survived) & self.valid_bits(word as u32);
+            }
+        }
+
+        self.next = std::mem::replace(&mut self.words, next);
+    }
+
+    /// Sets the `Rule` by which the `PackedUniverse` evolves.
+    ///
+    /// The `rule` is a rulestring in the B/S notation. Rules with
+    /// another neighbourhood than the 8 cell one or with dying
+    /// states are rejected.
+    pub fn set_rule(&mut self, rule: &str) -> Result<(), RuleError> {
+        let rule: Rule = rule.parse()?;
+        if !rule.is_life_like() {
+            return Err(RuleError::Unsupported(rule.to_string()));
+        }
+        self.rule = rule;
+        Ok(())
+    }
+
+    /// Returns the current `Rule` as a rulestring in the B/S notation.
+    pub fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /// Toggles the state of a cell.
+    pub fn toggle_cell(&mut self, row: u32, column: u32) {
+        let (idx, bit) = self.get_index(row, column);
+        self.words[idx] ^= 1 << bit;
+    }
+
+    /// Returns `true` if the cell in `row` and `column` is alive.
+    pub fn is_alive(&self, row: u32, column: u32) -> bool {
+        self.get_cell(row, column) == Cell::Alive
+    }
+
+    /// Returns the number of living cells.
+    pub fn population(&self) -> u32 {
+        self.words.iter().map(|word| word.count_ones()).sum()
+    }
+
+    /// Returns the `width` of the `PackedUniverse`.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the `height` of the `PackedUniverse`.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the number of bytes that make up a row,
+    /// including the unused bits at the end of it.
+    pub fn bytes_per_row(&self) -> u32 {
+        self.words_per_row * BITS / 8
+    }
+
+    /// Returns a raw pointer to the bit-packed cells.
+    pub fn cells(&self) -> *const u64 {
+        self.words.as_ptr()
+    }
+
+    /// Returns the length of the buffer behind `cells()` in bytes.
+    pub fn cells_len(&self) -> u32 {
+        self.bytes_per_row() * self.height
+    }
+}
@@ -0,0 +1,350 @@
+use wasm_bindgen::prelude::*;
+// We need the std::fmt tools to print errors:
+use std::fmt;
+// The size of a `Pattern` is computed in `u64` and narrowed afterwards:
+use std::convert::TryFrom;
+
+use super::cell::Cell;
+use super::rule::{Rule, RuleError};
+
+/// The largest number of cells a `Pattern` may have, which keeps
+/// malformed or hostile pattern files from allocating gigabytes.
+pub const MAX_CELLS: u64 = 1 << 26;
+
+/// A `Pattern` is a rectangular arrangement of `Cell` instances.
+///
+/// Patterns are the intermediate representation between the textual
+/// pattern formats (like RLE) and a `Universe`: every format is parsed
+/// into a `Pattern`, which can then be inserted into a `Universe`.
+/// Besides the cells, a `Pattern` keeps the metadata that the formats
+/// carry along, i.e. a name, comments and the rule it was made for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    width: u32,
+    height: u32,
+    cells: Vec<Cell>,
+    name: Option<String>,
+    comments: Vec<String>,
+    rule: Option<Rule>,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// The `Orientation` a `Pattern` is placed in.
+///
+/// These are the eight ways to rotate and mirror a rectangle. The
+/// rotations are clockwise and the mirrors keep the top left corner
+/// in place for `Transpose` and swap it with the bottom right one
+/// for `AntiTranspose`.
+pub enum Orientation {
+    /// The `Pattern` as it is.
+    Original,
+    /// Rotated by 90° clockwise.
+    Rotate90,
+    /// Rotated by 180°.
+    Rotate180,
+    /// Rotated by 270° clockwise, i.e. 90° counterclockwise.
+    Rotate270,
+    /// Mirrored from left to right.
+    FlipHorizontal,
+    /// Mirrored from top to bottom.
+    FlipVertical,
+    /// Mirrored along the diagonal from the top left corner,
+    /// which swaps rows and columns.
+    Transpose,
+    /// Mirrored along the diagonal from the top right corner.
+    AntiTranspose,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// How the cells of a pasted `Pattern` are combined
+/// with the cells that are already there.
+pub enum MergeMode {
+    /// A cell is alive if it was alive before or is alive in the
+    /// `Pattern`, so pasting never kills a cell.
+    Or,
+    /// A cell is alive if it's alive either before or in the `Pattern`,
+    /// but not in both. Pasting the same `Pattern` twice at the same
+    /// position undoes the first paste.
+    Xor,
+    /// The cells of the `Pattern` replace the ones that were there,
+    /// including its dead cells.
+    Overwrite,
+}
+
+impl MergeMode {
+    /// Returns the `Cell` that results from pasting
+    /// `pasted` onto `existing`.
+    pub fn merge(&self, existing: Cell, pasted: Cell) -> Cell {
+        let alive = match self {
+            MergeMode::Or => existing == Cell::Alive || pasted == Cell::Alive,
+            MergeMode::Xor => (existing == Cell::Alive) != (pasted == Cell::Alive),
+            MergeMode::Overwrite => pasted == Cell::Alive,
+        };
+        if alive {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+}
+
+/// The errors that can occur while reading or inserting a `Pattern`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatternError {
+    /// The header line (`x = .., y = ..`) couldn't be parsed.
+    InvalidHeader { line: usize },
+    /// A run count is zero, too large or not followed by a tag.
+    InvalidRunCount { line: usize },
+    /// A line that should hold a coordinate pair doesn't.
+    InvalidCoordinate { line: usize },
+    /// A character that has no meaning in the format.
+    UnexpectedCharacter { character: char, line: usize },
+    /// The cells reach beyond the size given in the header.
+    ExceedsHeader { width: u32, height: u32 },
+    /// The rule of the pattern couldn't be parsed.
+    InvalidRule(RuleError),
+    /// The pattern doesn't fit into the `Universe`.
+    TooLarge { width: u32, height: u32 },
+    /// There's no built-in pattern with this name.
+    UnknownPattern(String),
+    /// Nothing was copied to the clipboard before pasting.
+    EmptyClipboard,
+}
+
+impl Pattern {
+    /// Creates a `Pattern` of only dead cells.
+    ///
+    /// # Panics
+    /// Patterns with more than `MAX_CELLS` cells can't be created,
+    /// see `try_new` for a version that returns an error instead.
+    pub fn new(width: u32, height: u32) -> Pattern {
+        Pattern::try_new(width, height).expect("the pattern has too many cells")
+    }
+
+    /// Creates a `Pattern` of only dead cells.
+    ///
+    /// # Errors
+    /// Patterns with more than `MAX_CELLS` cells are rejected
+    /// with `PatternError::TooLarge`.
+    pub fn try_new(width: u32, height: u32) -> Result<Pattern, PatternError> {
+        let size = (width as u64)
+            .checked_mul(height as u64)
+            .filter(|&size| size <= MAX_CELLS)
+            .ok_or(PatternError::TooLarge { width, height })?;
+        Ok(Pattern {
+            width,
+            height,
+            cells: vec![Cell::Dead; size as usize],
+            name: None,
+            comments: Vec::new(),
+            rule: None,
+        })
+    }
+
+    /// Creates a `Pattern` that contains exactly the living cells at
+    /// the given `(row, column)` coordinates.
+    ///
+    /// The size of the `Pattern` is the smallest one that holds
+    /// all of the coordinates.
+    ///
+    /// # Errors
+    /// Coordinates that span more than `MAX_CELLS` cells, or more
+    /// rows or columns than an `u32` can count, are rejected with
+    /// `PatternError::TooLarge` before anything is allocated.
+    pub fn from_coordinates(coordinates: &[(u32, u32)]) -> Result<Pattern, PatternError> {
+        // The size is one more than the largest coordinate, which
+        // only fits into an `u64` for coordinates of `u32::MAX`:
+        let height = coordinates.iter().map(|&(row, _)| row as u64 + 1).max();
+        let width = coordinates
+            .iter()
+            .map(|&(_, column)| column as u64 + 1)
+            .max();
+        let (width, height) = (width.unwrap_or(0), height.unwrap_or(0));
+        let too_large = PatternError::TooLarge {
+            width: width.min(u32::MAX as u64) as u32,
+            height: height.min(u32::MAX as u64) as u32,
+        };
+        let width = u32::try_from(width).map_err(|_| too_large.clone())?;
+        let height = u32::try_from(height).map_err(|_| too_large)?;
+
+        let mut pattern = Pattern::try_new(width, height)?;
+        for &(row, column) in coordinates {
+            pattern.set(row, column, Cell::Alive);
+        }
+        Ok(pattern)
+    }
+
+    /// Returns the `width` of the `Pattern`.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the `height` of the `Pattern`.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the `Cell` in the given `row` and `column`.
+    pub fn get(&self, row: u32, column: u32) -> Cell {
+        self.cells[(row * self.width + column) as usize]
+    }
+
+    /// Sets the `Cell` in the given `row` and `column`.
+    pub fn set(&mut self, row: u32, column: u32, cell: Cell) {
+        self.cells[(row * self.width + column) as usize] = cell;
+    }
+
+    /// Returns the `(row, column)` coordinates of all living cells,
+    /// row by row.
+    pub fn live_cells(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, &cell)| cell == Cell::Alive)
+            .map(move |(idx, _)| (idx as u32 / width, idx as u32 % width))
+    }
+
+    /// Returns the number of living cells.
+    pub fn population(&self) -> u32 {
+        self.live_cells().count() as u32
+    }
+
+    /// Returns the name of the `Pattern`, if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Sets the name of the `Pattern`.
+    pub fn set_name(&mut self, name: Option<String>) {
+        self.name = name;
+    }
+
+    /// Returns the comments attached to the `Pattern`.
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    /// Attaches another comment line to the `Pattern`.
+    pub fn add_comment(&mut self, comment: String) {
+        self.comments.push(comment);
+    }
+
+    /// Returns the `Rule` the `Pattern` was made for, if it's known.
+    pub fn rule(&self) -> Option<&Rule> {
+        self.rule.as_ref()
+    }
+
+    /// Sets the `Rule` the `Pattern` was made for.
+    pub fn set_rule(&mut self, rule: Option<Rule>) {
+        self.rule = rule;
+    }
+
+    /// Returns a copy of the `Pattern` with all cells moved by
+    /// `delta_row` rows and `delta_column` columns.
+    ///
+    /// The size of the `Pattern` stays the same, so cells that are
+    /// moved beyond an edge wrap around to the opposite one.
+    pub fn translated(&self, delta_row: i64, delta_column: i64) -> Pattern {
+        let mut translated = Pattern {
+            cells: vec![Cell::Dead; self.cells.len()],
+            ..self.clone()
+        };
+        for (row, column) in self.live_cells() {
+            translated.set(
+                (row as i64 + delta_row).rem_euclid(self.height as i64) as u32,
+                (column as i64 + delta_column).rem_euclid(self.width as i64) as u32,
+                Cell::Alive,
+            );
+        }
+        translated
+    }
+
+    /// Returns a copy of the `Pattern` in the given `Orientation`.
+    ///
+    /// Rotating by 90° or 270° and both mirrors along a diagonal
+    /// swap the `width` and `height`. The metadata is kept.
+    pub fn oriented(&self, orientation: Orientation) -> Pattern {
+        let (width, height) = (self.width, self.height);
+        let (new_width, new_height) = match orientation {
+            Orientation::Rotate90
+            | Orientation::Rotate270
+            | Orientation::Transpose
+            | Orientation::AntiTranspose => (height, width),
+            _ => (width, height),
+        };
+
+        let mut oriented = Pattern {
+            cells: vec![Cell::Dead; self.cells.len()],
+            width: new_width,
+            height: new_height,
+            ..self.clone()
+        };
+        for (row, column) in self.live_cells() {
+            let (new_row, new_column) = match orientation {
+                Orientation::Original => (row, column),
+                Orientation::Rotate90 => (column, height - 1 - row),
+                Orientation::Rotate180 => (height - 1 - row, width - 1 - column),
+                Orientation::Rotate270 => (width - 1 - column, row),
+                Orientation::FlipHorizontal => (row, width - 1 - column),
+                Orientation::FlipVertical => (height - 1 - row, column),
+                Orientation::Transpose => (column, row),
+                Orientation::AntiTranspose => (width - 1 - column, height - 1 - row),
+            };
+            oriented.set(new_row, new_column, Cell::Alive);
+        }
+        oriented
+    }
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatternError::InvalidHeader { line } => {
+                write!(f, "invalid header in line {}", line)
+            }
+            PatternError::InvalidRunCount { line } => {
+                write!(f, "invalid run count in line {}", line)
+            }
+            PatternError::InvalidCoordinate { line } => {
+                write!(f, "invalid coordinates in line {}", line)
+            }
+            PatternError::UnexpectedCharacter { character, line } => {
+                write!(f, "unexpected character '{}' in line {}", character, line)
+            }
+            PatternError::ExceedsHeader { width, height } => write!(
+                f,
+                "the cells exceed the size of {}x{} given in the header",
+                width, height
+            ),
+            PatternError::InvalidRule(error) => write!(f, "invalid rule: {}", error),
+            PatternError::TooLarge { width, height } => write!(
+                f,
+                "a pattern of {}x{} cells doesn't fit into the universe",
+                width, height
+            ),
+            PatternError::UnknownPattern(name) => {
+                write!(f, "there's no built-in pattern called '{}'", name)
+            }
+            PatternError::EmptyClipboard => write!(f, "there's nothing on the clipboard to paste"),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+impl From<RuleError> for PatternError {
+    fn from(error: RuleError) -> PatternError {
+        PatternError::InvalidRule(error)
+    }
+}
+
+// Just like a `RuleError`, the error is thrown as
+// an exception with its message in JavaScript.
+impl From<PatternError> for JsValue {
+    fn from(error: PatternError) -> JsValue {
+        JsValue::from_str(&error.to_string())
+    }
+}
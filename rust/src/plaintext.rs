@@ -0,0 +1,124 @@
+//! Support for the plaintext (`.cells`) pattern format of the LifeWiki.
+//!
+//! A plaintext file starts with optional `!` comment lines, where the
+//! first one is usually `!Name: ..`. All other lines are the rows of the
+//! pattern, with a `.` for each dead and an `O` for each living cell.
+//! Rows may end early, in which case the remaining cells are dead.
+
+use super::cell::Cell;
+use super::pattern::{Pattern, PatternError};
+
+/// Parses a `Pattern` from a string in the plaintext format.
+///
+/// A `!Name:` comment sets the name of the `Pattern`, all other
+/// comments are kept as they are. Besides `O`, the parser also accepts
+/// `*` for living cells, which some older files use.
+pub fn parse(plaintext: &str) -> Result<Pattern, PatternError> {
+    parse_within(plaintext, u32::MAX, u32::MAX)
+}
+
+/// Parses a `Pattern` from a string in the plaintext format, which
+/// may be at most `max_width` cells wide and `max_height` high.
+///
+/// The width is checked while the rows are read, so a pattern that
+/// couldn't be inserted anyway is never built.
+///
+/// # Errors
+/// Patterns that are larger are rejected with `PatternError::TooLarge`,
+/// as are patterns with more than `MAX_CELLS` cells.
+pub fn parse_within(
+    plaintext: &str,
+    max_width: u32,
+    max_height: u32,
+) -> Result<Pattern, PatternError> {
+    let mut name = None;
+    let mut comments = Vec::new();
+    let mut rows: Vec<Vec<Cell>> = Vec::new();
+
+    for (idx, line) in plaintext.lines().enumerate() {
+        // Line numbers start at 1 for our error messages:
+        let line_number = idx + 1;
+        // Windows line endings leave a `\r` behind:
+        let line = line.trim_end();
+
+        if let Some(comment) = line.strip_prefix('!') {
+            match comment.strip_prefix("Name:") {
+                Some(pattern_name) if name.is_none() => {
+                    name = Some(pattern_name.trim().to_string())
+                }
+                _ => comments.push(comment.trim().to_string()),
+            }
+            continue;
+        }
+
+        let row = line
+            .chars()
+            .map(|character| match character {
+                '.' => Ok(Cell::Dead),
+                'O' | '*' => Ok(Cell::Alive),
+                _ => Err(PatternError::UnexpectedCharacter {
+                    character,
+                    line: line_number,
+                }),
+            })
+            .collect::<Result<Vec<Cell>, PatternError>>()?;
+        if row.len() > max_width as usize {
+            return Err(PatternError::TooLarge {
+                width: row.len().min(u32::MAX as usize) as u32,
+                height: (rows.len() + 1).min(u32::MAX as usize) as u32,
+            });
+        }
+        rows.push(row);
+    }
+
+    // Empty lines at the end of a file aren't part of the pattern:
+    while rows.last().is_some_and(|row| row.is_empty()) {
+        rows.pop();
+    }
+
+    // Every row fits into the width, so only the height is left:
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0) as u32;
+    let height = rows.len().min(u32::MAX as usize) as u32;
+    if rows.len() > max_height as usize {
+        return Err(PatternError::TooLarge { width, height });
+    }
+    let mut pattern = Pattern::try_new(width, height)?;
+    for (row, cells) in rows.iter().enumerate() {
+        for (column, &cell) in cells.iter().enumerate() {
+            pattern.set(row as u32, column as u32, cell);
+        }
+    }
+    pattern.set_name(name);
+    for comment in comments {
+        pattern.add_comment(comment);
+    }
+
+    Ok(pattern)
+}
+
+/// Writes a `Pattern` as a string in the plaintext format.
+///
+/// The plaintext format has no notion of rules, so the `Rule`
+/// of the `Pattern` is left out.
+pub fn write(pattern: &Pattern) -> String {
+    let mut plaintext = String::new();
+
+    if let Some(name) = pattern.name() {
+        plaintext.push_str(&format!("!Name: {}\n", name));
+    }
+    for comment in pattern.comments() {
+        plaintext.push_str(&format!("!{}\n", comment));
+    }
+
+    for row in 0..pattern.height() {
+        for column in 0..pattern.width() {
+            plaintext.push(match pattern.get(row, column) {
+                Cell::Alive => 'O',
+                Cell::Dead => '.',
+            });
+        }
+        plaintext.push('\n');
+    }
+
+    plaintext
+}
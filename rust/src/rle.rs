@@ -0,0 +1,398 @@
+//! Support for the Run Length Encoded (RLE) pattern format.
+//!
+//! An RLE file consists of optional `#` comment lines, a header line
+//! like `x = 3, y = 3, rule = B3/S23` and the encoded cells. The cells
+//! are written row by row as runs of `b` (dead) and `o` (alive) tags,
+//! each optionally preceded by a run count. Rows end with a `$` and the
+//! pattern ends with a `!`.
+//!
+//! Patterns of rules with more than two states use the multi-state
+//! tags of Golly instead: `.` for dead cells, `A` to `X` for the states
+//! 1 to 24 and a prefix from `p` upwards for every 24 higher states.
+
+use super::cell::Cell;
+use super::pattern::{Pattern, PatternError};
+
+/// Parses a `Pattern` from a string in the RLE format.
+///
+/// `#N` lines set the name of the `Pattern`, `#C` and `#c` lines are
+/// kept as comments. All other `#` lines are ignored. Without a header
+/// line, the size of the `Pattern` is derived from its cells.
+pub fn parse(rle: &str) -> Result<Pattern, PatternError> {
+    parse_within(rle, u32::MAX, u32::MAX)
+}
+
+/// Parses a `Pattern` from a string in the RLE format, which
+/// may be at most `max_width` cells wide and `max_height` high.
+///
+/// The size is checked while the cells are read, so a pattern that
+/// couldn't be inserted anyway is never built.
+///
+/// # Errors
+/// Patterns that are larger, according to their header or their
+/// cells, are rejected with `PatternError::TooLarge`. Run counts that
+/// move beyond the largest possible row or column are rejected with
+/// `PatternError::InvalidRunCount`.
+pub fn parse_within(rle: &str, max_width: u32, max_height: u32) -> Result<Pattern, PatternError> {
+    parse_states_within(rle, max_width, max_height).map(|(pattern, _)| pattern)
+}
+
+/// Parses a `Pattern` together with the state of each of its cells
+/// from a string in the RLE format, just like `parse_within`.
+///
+/// The states are the ones `write_states` writes, one per cell, row
+/// by row: 0 for dead cells, 1 for living ones and 2 upwards for the
+/// dying stages, which are dead cells in the `Pattern`. The two-state
+/// tags `b` and `o` are read as the states 0 and 1.
+///
+/// # Errors
+/// Besides the errors of `parse_within`, states above 255 and prefixes
+/// that aren't followed by a letter from `A` to `X` are rejected with
+/// `PatternError::UnexpectedCharacter`.
+pub fn parse_states_within(
+    rle: &str,
+    max_width: u32,
+    max_height: u32,
+) -> Result<(Pattern, Vec<u8>), PatternError> {
+    let mut name = None;
+    let mut comments = Vec::new();
+    let mut header: Option<(u32, u32, Option<String>)> = None;
+
+    // The runs of cells that aren't dead we've found so far as `(row,
+    // column, count, state)` and the position of the next cell:
+    let mut runs = Vec::new();
+    let (mut row, mut column) = (0u32, 0u32);
+    let (mut width, mut height) = (0u32, 0u32);
+
+    // A run count and a multi-state prefix that still wait for their
+    // tag and the line we're reading, which is the line reported in errors:
+    let mut run_count: Option<u32> = None;
+    let mut prefix: Option<char> = None;
+    let mut last_line = 0;
+
+    'lines: for (idx, line) in rle.lines().enumerate() {
+        // Line numbers start at 1 for our error messages:
+        last_line = idx + 1;
+        let line = line.trim();
+
+        if let Some(comment) = line.strip_prefix('#') {
+            let mut chars = comment.chars();
+            match chars.next() {
+                Some('N') => name = Some(chars.as_str().trim().to_string()),
+                Some('C') | Some('c') => comments.push(chars.as_str().trim().to_string()),
+                _ => {}
+            }
+            continue;
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+
+        // The header is the first line that isn't a comment:
+        if header.is_none() && width == 0 && line.starts_with('x') {
+            let (header_width, header_height, header_rule) = parse_header(line, last_line)?;
+            if header_width > max_width || header_height > max_height {
+                return Err(PatternError::TooLarge {
+                    width: header_width,
+                    height: header_height,
+                });
+            }
+            header = Some((header_width, header_height, header_rule));
+            continue;
+        }
+
+        for character in line.chars() {
+            if let Some(digit) = character.to_digit(10).filter(|_| prefix.is_none()) {
+                let count = run_count
+                    .unwrap_or(0)
+                    .checked_mul(10)
+                    .and_then(|count| count.checked_add(digit))
+                    .ok_or(PatternError::InvalidRunCount { line: last_line })?;
+                run_count = Some(count);
+                continue;
+            }
+
+            if character.is_whitespace() {
+                continue;
+            }
+
+            // A prefix like the `p` in `pA` belongs to the letter after it:
+            if prefix.is_none() && ('p'..='y').contains(&character) {
+                prefix = Some(character);
+                continue;
+            }
+            let state = match (prefix.take(), character) {
+                (None, 'b') | (None, '.') => Some(0),
+                (None, 'o') => Some(1),
+                (prefix, 'A'..='X') => {
+                    // `p` stands for 24 more states than no prefix at all:
+                    let prefix = prefix.map_or(0, |prefix| prefix as u32 - 'p' as u32 + 1);
+                    let state = prefix * 24 + (character as u32 - 'A' as u32 + 1);
+                    if state > u8::MAX as u32 {
+                        return Err(PatternError::UnexpectedCharacter {
+                            character,
+                            line: last_line,
+                        });
+                    }
+                    Some(state as u8)
+                }
+                (None, _) => None,
+                (Some(_), _) => {
+                    return Err(PatternError::UnexpectedCharacter {
+                        character,
+                        line: last_line,
+                    })
+                }
+            };
+
+            // A tag without a run count stands for a single cell:
+            let explicit_count = run_count.take();
+            let count = explicit_count.unwrap_or(1);
+            if count == 0 {
+                return Err(PatternError::InvalidRunCount { line: last_line });
+            }
+
+            let invalid_count = || PatternError::InvalidRunCount { line: last_line };
+            match (state, character) {
+                (Some(state), _) => {
+                    if state > 0 {
+                        runs.push((row, column, count, state));
+                    }
+                    column = column.checked_add(count).ok_or_else(invalid_count)?;
+                    // Keep track of the size the cells span:
+                    width = width.max(column);
+                    height = height.max(row.checked_add(1).ok_or_else(invalid_count)?);
+                    if width > max_width || height > max_height {
+                        return Err(PatternError::TooLarge { width, height });
+                    }
+                }
+                (None, '$') => {
+                    row = row.checked_add(count).ok_or_else(invalid_count)?;
+                    column = 0;
+                }
+                (None, '!') if explicit_count.is_none() => break 'lines,
+                (None, '!') => return Err(PatternError::InvalidRunCount { line: last_line }),
+                _ => {
+                    return Err(PatternError::UnexpectedCharacter {
+                        character,
+                        line: last_line,
+                    })
+                }
+            }
+        }
+    }
+
+    // A run count or prefix at the very end has no tag it could belong to:
+    if let Some(character) = prefix {
+        return Err(PatternError::UnexpectedCharacter {
+            character,
+            line: last_line,
+        });
+    }
+    if run_count.is_some() {
+        return Err(PatternError::InvalidRunCount { line: last_line });
+    }
+
+    let mut rule = None;
+    if let Some((header_width, header_height, header_rule)) = header {
+        if width > header_width || height > header_height {
+            return Err(PatternError::ExceedsHeader {
+                width: header_width,
+                height: header_height,
+            });
+        }
+        width = header_width;
+        height = header_height;
+        if let Some(header_rule) = header_rule {
+            rule = Some(header_rule.parse()?);
+        }
+    }
+
+    let mut pattern = Pattern::try_new(width, height)?;
+    let mut states = vec![0; width as usize * height as usize];
+    for (row, start, count, state) in runs {
+        for column in start..start + count {
+            if state == 1 {
+                pattern.set(row, column, Cell::Alive);
+            }
+            states[row as usize * width as usize + column as usize] = state;
+        }
+    }
+    pattern.set_name(name);
+    for comment in comments {
+        pattern.add_comment(comment);
+    }
+    pattern.set_rule(rule);
+
+    Ok((pattern, states))
+}
+
+/// Parses the header line of an RLE pattern.
+///
+/// Returns the `width` (`x`), `height` (`y`) and the rulestring,
+/// if there is one.
+fn parse_header(
+    line: &str,
+    line_number: usize,
+) -> Result<(u32, u32, Option<String>), PatternError> {
+    let invalid = PatternError::InvalidHeader { line: line_number };
+    let (mut width, mut height, mut rule) = (None, None, None);
+
+    // The rule is the last entry of the header and rulestrings in the
+    // HROT notation contain commas themselves, so we split it off first:
+    let (line, rule_entry) = match line.find("rule") {
+        Some(start) => (
+            line[..start].trim_end().trim_end_matches(','),
+            Some(&line[start..]),
+        ),
+        None => (line, None),
+    };
+    if let Some(entry) = rule_entry {
+        let (_, value) = entry.split_once('=').ok_or(invalid.clone())?;
+        rule = Some(value.trim().to_string());
+    }
+
+    for entry in line.split(',') {
+        let (key, value) = entry.split_once('=').ok_or(invalid.clone())?;
+        let value = value.trim();
+        match key.trim() {
+            "x" => width = Some(value.parse::<u32>().map_err(|_| invalid.clone())?),
+            "y" => height = Some(value.parse::<u32>().map_err(|_| invalid.clone())?),
+            _ => return Err(invalid),
+        }
+    }
+
+    match (width, height) {
+        (Some(width), Some(height)) => Ok((width, height, rule)),
+        _ => Err(invalid),
+    }
+}
+
+/// The maximum length of a line of encoded cells, as recommended
+/// by the RLE specification.
+const MAX_LINE_LENGTH: usize = 70;
+
+/// Writes a `Pattern` as a string in the RLE format.
+///
+/// The name and comments of the `Pattern` are written as `#N` and `#C`
+/// lines in front of the header. The header contains the `Rule` of the
+/// `Pattern` or `B3/S23` if it doesn't have one. Dead cells at the end
+/// of a row are omitted and empty rows are merged into a single `$`
+/// tag with a run count, just like other tools do it.
+pub fn write(pattern: &Pattern) -> String {
+    write_tags(
+        pattern,
+        |row, column| match pattern.get(row, column) {
+            Cell::Alive => "o".to_string(),
+            Cell::Dead => "b".to_string(),
+        },
+        "b",
+    )
+}
+
+/// Writes a `Pattern` together with the state of each of its cells as
+/// a string in the multi-state RLE format of Generations rules.
+///
+/// The `states` hold one state per cell, row by row: 0 for dead cells,
+/// 1 for living ones and 2 upwards for the dying stages. Dead cells are
+/// written as `.` and the states 1 to 24 as the letters `A` to `X`.
+/// Higher states get a prefix from `p` upwards for every 24 states, e.g.
+/// `pA` for 25, just like Golly does it. Everything else works like in
+/// `write`.
+///
+/// # Panics
+/// There has to be exactly one state per cell.
+pub fn write_states(pattern: &Pattern, states: &[u8]) -> String {
+    assert_eq!(
+        states.len(),
+        pattern.width() as usize * pattern.height() as usize,
+        "there has to be one state per cell"
+    );
+    write_tags(
+        pattern,
+        |row, column| match states[(row * pattern.width() + column) as usize] {
+            0 => ".".to_string(),
+            state @ 1..=24 => ((b'A' + state - 1) as char).to_string(),
+            state => {
+                let (prefix, letter) = ((state - 25) / 24, (state - 25) % 24);
+                format!("{}{}", (b'p' + prefix) as char, (b'A' + letter) as char)
+            }
+        },
+        ".",
+    )
+}
+
+/// Writes the name, comments and header of a `Pattern`, followed
+/// by the `tag` of each of its cells, where `dead` is the tag of
+/// dead cells that can be left out at the end of a row.
+fn write_tags(pattern: &Pattern, tag: impl Fn(u32, u32) -> String, dead: &str) -> String {
+    let mut rle = String::new();
+
+    if let Some(name) = pattern.name() {
+        rle.push_str(&format!("#N {}\n", name));
+    }
+    for comment in pattern.comments() {
+        rle.push_str(&format!("#C {}\n", comment));
+    }
+
+    let rule = pattern.rule().cloned().unwrap_or_default();
+    rle.push_str(&format!(
+        "x = {}, y = {}, rule = {}\n",
+        pattern.width(),
+        pattern.height(),
+        rule
+    ));
+
+    /// Formats a run of `count` equal tags.
+    fn run(count: u32, tag: &str) -> String {
+        if count == 1 {
+            tag.to_string()
+        } else {
+            format!("{}{}", count, tag)
+        }
+    }
+
+    // First we collect all runs as separate tokens:
+    let mut tokens = Vec::new();
+    // The number of row ends we haven't written yet:
+    let mut row_ends = 0;
+    for row in 0..pattern.height() {
+        let mut column = 0;
+        while column < pattern.width() {
+            let cell_tag = tag(row, column);
+            let count = (column..pattern.width())
+                .take_while(|&next| tag(row, next) == cell_tag)
+                .count() as u32;
+
+            // Dead cells at the end of a row are implied by the `$`:
+            if cell_tag == dead && column + count == pattern.width() {
+                break;
+            }
+
+            if row_ends > 0 {
+                tokens.push(run(row_ends, "$"));
+                row_ends = 0;
+            }
+            tokens.push(run(count, &cell_tag));
+            column += count;
+        }
+        row_ends += 1;
+    }
+    tokens.push("!".to_string());
+
+    // And then wrap the tokens into lines, without splitting a run:
+    let mut line = String::new();
+    for token in tokens {
+        if line.len() + token.len() > MAX_LINE_LENGTH {
+            rle.push_str(&line);
+            rle.push('\n');
+            line.clear();
+        }
+        line.push_str(&token);
+    }
+    rle.push_str(&line);
+    rle.push('\n');
+
+    rle
+}
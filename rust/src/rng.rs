@@ -0,0 +1,48 @@
+//! A small pseudo random number generator for reproducible universes.
+//!
+//! The generators of the `rand` crate may change their output between
+//! versions and platforms, so a seed alone isn't enough to recreate a
+//! random universe. SplitMix64 is simple enough to implement here and
+//! produces the same numbers on every target, including WASM.
+
+/// The SplitMix64 pseudo random number generator.
+///
+/// # Algorithm explanation
+///
+/// The state is a counter that advances by a fixed odd constant (the
+/// golden ratio scaled to 64 bits) with every call. Each counter value
+/// is then scrambled by a couple of xor-shifts and multiplications,
+/// which spread every bit of the counter over the whole output.
+#[derive(Clone, Debug)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    /// Creates a generator that starts from the given `seed`.
+    pub fn new(seed: u64) -> SplitMix64 {
+        SplitMix64 { state: seed }
+    }
+
+    /// Returns the next pseudo random number.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a pseudo random number in the range `0.0..1.0`.
+    ///
+    /// We only keep the upper 53 bits, which is exactly the precision
+    /// of an `f64`, so all values are spaced evenly.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns `true` with the given `probability`.
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+}
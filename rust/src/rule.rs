@@ -0,0 +1,649 @@
+use wasm_bindgen::prelude::*;
+// We need the std::fmt tools to print rules
+// and errors in their rulestring notation:
+use std::fmt;
+use std::str::FromStr;
+
+use super::neighborhood::{Neighborhood, NeighborhoodKind, MAX_RANGE};
+
+/// A `Rule` determines how each `Cell` of a `Universe` evolves.
+///
+/// Life-like rules are written in the B/S notation, e.g. `B3/S23`
+/// for Conway's Game of Life: a dead cell is born with exactly 3
+/// living neighbours, a living cell survives with 2 or 3. A suffix of
+/// `H` or `V` switches to the hexagonal or von Neumann neighbourhood.
+///
+/// Rules for neighbourhoods with a larger range are written in the
+/// HROT notation, e.g. `R2,C2,S6-9,B7-8,NM`, which lists the range,
+/// the number of states, the survival and birth conditions and the
+/// kind of neighbourhood (`M` for Moore, `N` for von Neumann and
+/// `H` for hexagonal).
+///
+/// Larger than Life rules are written in a similar notation, e.g.
+/// `R5,C0,M1,S34..58,B34..45,NM` for Bosco's Rule. The `M1` states that
+/// a living cell counts itself as one of its neighbours, so it survives
+/// with 33 to 57 living cells around it.
+///
+/// Generations rules add a third part with the number of states, e.g.
+/// `B2/S/C3` for Brian's Brain, which can also be written as `/2/3` in
+/// the S/B/C notation. A living cell that doesn't survive doesn't die
+/// right away, but passes through the dying states in between. Dying
+/// cells don't count as living neighbours and can't be born again
+/// until they're dead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    birth: Counts,
+    survival: Counts,
+    neighborhood: Neighborhood,
+    // Whether the cell itself is part of its neighbourhood. This only
+    // matters for living cells, as a dead cell doesn't add to the count:
+    includes_middle: bool,
+    // The number of states including the living and the dead one,
+    // which is 2 for all rules without dying states:
+    states: u8,
+}
+
+/// A set of neighbour counts, stored as a bitset.
+///
+/// Bit `n` of the set is set if `n` living neighbours satisfy
+/// the condition. The bitset grows with the largest count, so
+/// it works for neighbourhoods of any size.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Counts {
+    words: Vec<u64>,
+}
+
+/// The birth and survival conditions of a `Rule`, unpacked into
+/// lookup tables for the inner loop of a tick.
+///
+/// Entry `n` of a table tells whether a cell with `n` living neighbours
+/// is born or survives. The tables cover every count the neighbourhood
+/// allows, so looking one up is a single index instead of a search
+/// through the words of a `Counts` bitset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transitions {
+    birth: Vec<bool>,
+    // Shifted by one for rules that count the cell itself,
+    // so that it's indexed by the neighbours alone:
+    survival: Vec<bool>,
+    // The last dying stage, which is 0 for two-state rules:
+    last_dying: u8,
+}
+
+/// The errors that can occur while parsing a rulestring.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuleError {
+    /// The rulestring was empty.
+    Empty,
+    /// The rulestring has no `/` between its birth and survival part.
+    MissingSlash,
+    /// Only one of the two parts carries a `B` or `S` prefix.
+    MixedNotation,
+    /// The same prefix (`B` or `S`) was used for both parts.
+    DuplicatePrefix(char),
+    /// A neighbour count larger than the neighbourhood was requested.
+    CountOutOfRange(u32),
+    /// A character that has no meaning in a rulestring.
+    UnexpectedCharacter(char),
+    /// A neighbourhood range of 0, one above `MAX_RANGE` or a missing range.
+    InvalidRange(u32),
+    /// A number of states below 2 or above 255.
+    InvalidStates(u32),
+    /// A part of a rulestring in the HROT notation that
+    /// couldn't be parsed.
+    InvalidToken(String),
+    /// The rule is valid, but can't be run by the engine it was
+    /// given to, e.g. a `B0` rule in an unbounded universe.
+    Unsupported(String),
+}
+
+impl Counts {
+    /// Adds a neighbour count to the set.
+    fn insert(&mut self, count: u32) {
+        let word = (count / 64) as usize;
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        self.words[word] |= 1 << (count % 64);
+    }
+
+    /// Returns `true` if the neighbour count is part of the set.
+    fn contains(&self, count: u32) -> bool {
+        self.words
+            .get((count / 64) as usize)
+            .is_some_and(|word| word & (1 << (count % 64)) != 0)
+    }
+
+    /// Returns all neighbour counts in the set in ascending order.
+    fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.words.len() as u32 * 64).filter(move |&count| self.contains(count))
+    }
+
+    /// Returns the neighbour counts as a list of ranges like `2-3,5`,
+    /// where `separator` goes between the start and end of a range.
+    fn to_ranges(&self, separator: &str) -> String {
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        for count in self.iter() {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == count => *end = count,
+                _ => ranges.push((count, count)),
+            }
+        }
+
+        ranges
+            .iter()
+            .map(|&(start, end)| {
+                if start == end {
+                    start.to_string()
+                } else {
+                    format!("{}{}{}", start, separator, end)
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+impl Rule {
+    /// Creates a `Rule` from the neighbour counts that
+    /// lead to a birth and those that let a cell survive,
+    /// for the 8 cell Moore neighbourhood.
+    ///
+    /// # Panics
+    /// The method panics if one of the counts is larger than 8.
+    pub fn new(birth: &[u8], survival: &[u8]) -> Rule {
+        fn counts(counts: &[u8]) -> Counts {
+            let mut set = Counts::default();
+            for &count in counts {
+                assert!(count <= 8, "a cell can't have more than 8 neighbours");
+                set.insert(count as u32);
+            }
+            set
+        }
+
+        Rule {
+            birth: counts(birth),
+            survival: counts(survival),
+            neighborhood: Neighborhood::default(),
+            includes_middle: false,
+            states: 2,
+        }
+    }
+
+    /// Returns the `Neighborhood` the neighbours are counted in.
+    pub fn neighborhood(&self) -> Neighborhood {
+        self.neighborhood
+    }
+
+    /// Sets the `Neighborhood` the neighbours are counted in,
+    /// keeping the birth and survival conditions.
+    ///
+    /// # Errors
+    /// If a condition asks for more neighbours than the new
+    /// `Neighborhood` has, the rule is left unchanged and
+    /// `RuleError::CountOutOfRange` is returned.
+    pub fn set_neighborhood(&mut self, neighborhood: Neighborhood) -> Result<(), RuleError> {
+        self.check_counts(neighborhood)?;
+        self.neighborhood = neighborhood;
+        Ok(())
+    }
+
+    /// Returns `true` for two-state rules in the 8 cell Moore
+    /// neighbourhood, which many engines are specialized on.
+    pub fn is_life_like(&self) -> bool {
+        self.neighborhood.is_life_like() && !self.includes_middle && self.states == 2
+    }
+
+    /// Returns the number of states, including the living and
+    /// the dead one.
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    /// Returns `true` if a living cell counts itself as a neighbour.
+    pub fn includes_middle(&self) -> bool {
+        self.includes_middle
+    }
+
+    /// Returns `true` if a dead cell with `live_neighbors`
+    /// living neighbours is born.
+    pub fn is_birth(&self, live_neighbors: u32) -> bool {
+        self.birth.contains(live_neighbors)
+    }
+
+    /// Returns `true` if a living cell with `live_neighbors`
+    /// living neighbours survives.
+    ///
+    /// The `live_neighbors` never include the cell itself, if the
+    /// `Rule` counts it we add it here.
+    pub fn is_survival(&self, live_neighbors: u32) -> bool {
+        self.survival
+            .contains(live_neighbors + self.includes_middle as u32)
+    }
+
+    /// Returns the state of a `Cell` at the next tick in time.
+    pub fn next_cell(&self, cell: super::cell::Cell, live_neighbors: u32) -> super::cell::Cell {
+        match cell {
+            super::cell::Cell::Dead if self.is_birth(live_neighbors) => super::cell::Cell::Alive,
+            super::cell::Cell::Alive if !self.is_survival(live_neighbors) => {
+                super::cell::Cell::Dead
+            }
+            // All other cells remain in the same state.
+            otherwise => otherwise,
+        }
+    }
+
+    /// Returns the state of a `Cell` and its dying stage at the next
+    /// tick in time, for rules with dying states.
+    ///
+    /// The dying stage of a living or dead cell is 0, dying cells count
+    /// up from 1 with each tick. A cell that reaches the last dying
+    /// stage is dead at the next tick.
+    pub fn next_state(
+        &self,
+        cell: super::cell::Cell,
+        dying: u8,
+        live_neighbors: u32,
+    ) -> (super::cell::Cell, u8) {
+        // The dying stages come after the living state:
+        let last_dying = self.states - 2;
+        match (cell, dying) {
+            (super::cell::Cell::Alive, 0)
+                if last_dying > 0 && !self.is_survival(live_neighbors) =>
+            {
+                (super::cell::Cell::Dead, 1)
+            }
+            (_, 0) => (self.next_cell(cell, live_neighbors), 0),
+            (_, stage) if stage < last_dying => (super::cell::Cell::Dead, stage + 1),
+            // The last dying stage is followed by the dead state:
+            _ => (super::cell::Cell::Dead, 0),
+        }
+    }
+
+    /// Returns the `Transitions` of the `Rule`, which are meant to be
+    /// built once per tick and looked up for every cell.
+    pub fn transitions(&self) -> Transitions {
+        let counts = 0..=self.neighborhood.size();
+        Transitions {
+            birth: counts.clone().map(|count| self.is_birth(count)).collect(),
+            survival: counts.map(|count| self.is_survival(count)).collect(),
+            last_dying: self.states - 2,
+        }
+    }
+
+    /// Checks that no count of the conditions exceeds the number of
+    /// cells in the `neighborhood`, including the cell itself for the
+    /// survival conditions of rules that count it.
+    fn check_counts(&self, neighborhood: Neighborhood) -> Result<(), RuleError> {
+        let size = neighborhood.size();
+        let survival_size = size + self.includes_middle as u32;
+        match (self.birth.iter().max(), self.survival.iter().max()) {
+            (Some(count), _) if count > size => Err(RuleError::CountOutOfRange(count)),
+            (_, Some(count)) if count > survival_size => Err(RuleError::CountOutOfRange(count)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that no count exceeds the size of the neighbourhood.
+    fn validate(self) -> Result<Rule, RuleError> {
+        self.check_counts(self.neighborhood)?;
+        Ok(self)
+    }
+
+    /// Parses a rulestring in the HROT notation, e.g. `R2,C2,S6-9,B7-8,NM`,
+    /// or in the Larger than Life notation, e.g. `R5,C0,M1,S34..58,B34..45,NM`.
+    ///
+    /// The survival and birth conditions are lists of counts and ranges
+    /// of counts, separated by commas just like the other parts. Ranges
+    /// are written with either `-` or `..` between their start and end.
+    fn parse_hrot(rulestring: &str) -> Result<Rule, RuleError> {
+        let invalid = |token: &str| RuleError::InvalidToken(token.to_string());
+        let parse_number =
+            |token: &str, number: &str| number.parse::<u32>().map_err(|_| invalid(token));
+
+        let mut range = None;
+        let mut kind = NeighborhoodKind::Moore;
+        let mut includes_middle = false;
+        let mut states = 2;
+        // The entries are only inserted once the range is known, as
+        // a range like `3-4000000000` would take ages otherwise:
+        let mut entries = Vec::new();
+        // The conditions the next list entry belongs to:
+        let mut current: Option<char> = None;
+
+        for token in rulestring.split(',').map(str::trim) {
+            let mut chars = token.chars();
+            let prefix = chars.next().map(|c| c.to_ascii_uppercase());
+            let rest = chars.as_str();
+
+            let entry = match prefix {
+                Some('R') => {
+                    range = Some(parse_number(token, rest)?);
+                    None
+                }
+                Some('C') => {
+                    // Both 0 and 2 stand for two states:
+                    states = match parse_number(token, rest)? {
+                        0 => 2,
+                        count => parse_states(count)?,
+                    };
+                    None
+                }
+                Some('M') => {
+                    includes_middle = match rest {
+                        "0" => false,
+                        "1" => true,
+                        _ => return Err(invalid(token)),
+                    };
+                    None
+                }
+                Some('S') | Some('B') => {
+                    current = prefix;
+                    Some(rest).filter(|rest| !rest.is_empty())
+                }
+                Some('N') => {
+                    kind = match rest {
+                        "M" | "m" => NeighborhoodKind::Moore,
+                        "N" | "n" => NeighborhoodKind::VonNeumann,
+                        "H" | "h" => NeighborhoodKind::Hexagonal,
+                        _ => return Err(invalid(token)),
+                    };
+                    None
+                }
+                Some(c) if c.is_ascii_digit() => Some(token),
+                _ => return Err(invalid(token)),
+            };
+
+            // An entry is a single count or a range like `6-9` or `6..9`:
+            if let Some(entry) = entry {
+                let (start, end) = match entry.split_once("..").or_else(|| entry.split_once('-')) {
+                    Some((start, end)) => (parse_number(token, start)?, parse_number(token, end)?),
+                    None => {
+                        let count = parse_number(token, entry)?;
+                        (count, count)
+                    }
+                };
+                match current {
+                    Some(conditions) => entries.push((conditions, start, end)),
+                    None => return Err(invalid(token)),
+                }
+            }
+        }
+
+        let range = match range {
+            Some(range) if range > 0 && range <= MAX_RANGE => range,
+            _ => return Err(RuleError::InvalidRange(range.unwrap_or(0))),
+        };
+        let neighborhood = Neighborhood::new(kind, range);
+
+        let (mut birth, mut survival) = (Counts::default(), Counts::default());
+        for (conditions, start, end) in entries {
+            let (counts, size) = if conditions == 'S' {
+                (&mut survival, neighborhood.size() + includes_middle as u32)
+            } else {
+                (&mut birth, neighborhood.size())
+            };
+            if end > size {
+                return Err(RuleError::CountOutOfRange(end));
+            }
+            for count in start..=end {
+                counts.insert(count);
+            }
+        }
+
+        Rule {
+            birth,
+            survival,
+            neighborhood,
+            includes_middle,
+            states,
+        }
+        .validate()
+    }
+}
+
+impl Transitions {
+    /// Returns the state of a `Cell` and its dying stage at the next
+    /// tick in time, just like `Rule::next_state`.
+    ///
+    /// # Panics
+    /// The `live_neighbors` can't exceed the size of the neighbourhood.
+    #[inline]
+    pub fn next_state(
+        &self,
+        cell: super::cell::Cell,
+        dying: u8,
+        live_neighbors: u32,
+    ) -> (super::cell::Cell, u8) {
+        let live_neighbors = live_neighbors as usize;
+        match (cell, dying) {
+            (super::cell::Cell::Alive, 0) if self.survival[live_neighbors] => {
+                (super::cell::Cell::Alive, 0)
+            }
+            // A living cell that doesn't survive starts dying, if
+            // there are any dying states:
+            (super::cell::Cell::Alive, 0) if self.last_dying > 0 => (super::cell::Cell::Dead, 1),
+            (super::cell::Cell::Alive, 0) => (super::cell::Cell::Dead, 0),
+            (super::cell::Cell::Dead, 0) if self.birth[live_neighbors] => {
+                (super::cell::Cell::Alive, 0)
+            }
+            (super::cell::Cell::Dead, 0) => (super::cell::Cell::Dead, 0),
+            (_, stage) if stage < self.last_dying => (super::cell::Cell::Dead, stage + 1),
+            // The last dying stage is followed by the dead state:
+            _ => (super::cell::Cell::Dead, 0),
+        }
+    }
+}
+
+/// Checks that a rule with `count` states can be stored,
+/// which needs at least a living and a dead state.
+fn parse_states(count: u32) -> Result<u8, RuleError> {
+    match count {
+        2..=255 => Ok(count as u8),
+        _ => Err(RuleError::InvalidStates(count)),
+    }
+}
+
+/// Conway's Game of Life (`B3/S23`) is the default `Rule`.
+impl Default for Rule {
+    fn default() -> Rule {
+        Rule::new(&[3], &[2, 3])
+    }
+}
+
+impl FromStr for Rule {
+    type Err = RuleError;
+
+    /// Parses a rulestring in the B/S or the HROT notation.
+    ///
+    /// Besides `B36/S23` the parser accepts the prefixes in any order and
+    /// case (`s23/b36`) as well as the older S/B notation without any
+    /// prefixes, where the survival conditions come first (`23/36`).
+    /// Generations rules have the number of states as a third part,
+    /// e.g. `B2/S/C3` or `/2/3`.
+    fn from_str(rulestring: &str) -> Result<Rule, RuleError> {
+        let rulestring = rulestring.trim();
+        if rulestring.is_empty() {
+            return Err(RuleError::Empty);
+        }
+
+        if rulestring.starts_with(['R', 'r']) && rulestring.contains(',') {
+            return Rule::parse_hrot(rulestring);
+        }
+
+        // A trailing letter selects another neighbourhood of range 1:
+        let (rulestring, kind) = match rulestring.chars().last() {
+            Some('H') | Some('h') => (
+                &rulestring[..rulestring.len() - 1],
+                NeighborhoodKind::Hexagonal,
+            ),
+            Some('V') | Some('v') => (
+                &rulestring[..rulestring.len() - 1],
+                NeighborhoodKind::VonNeumann,
+            ),
+            _ => (rulestring, NeighborhoodKind::Moore),
+        };
+
+        // Generations rules have a third part with the number of states,
+        // which may carry a `C` or `G` prefix:
+        let (first, second, states) = match *rulestring.split('/').collect::<Vec<&str>>() {
+            [_] => return Err(RuleError::MissingSlash),
+            [first, second] => (first, second, 2),
+            [first, second, states] => {
+                let number = states.trim_start_matches(['C', 'c', 'G', 'g']);
+                let count = number
+                    .parse::<u32>()
+                    .map_err(|_| RuleError::InvalidToken(states.to_string()))?;
+                (first, second, parse_states(count)?)
+            }
+            _ => return Err(RuleError::InvalidToken(rulestring.to_string())),
+        };
+
+        /// Splits the prefix off a part of the rulestring and
+        /// turns its digits into a set of neighbour counts.
+        fn parse_part(part: &str) -> Result<(Option<char>, Counts), RuleError> {
+            let mut chars = part.chars().peekable();
+            let prefix = match chars.peek() {
+                Some(c) if c.eq_ignore_ascii_case(&'b') || c.eq_ignore_ascii_case(&'s') => {
+                    let prefix = c.to_ascii_uppercase();
+                    chars.next();
+                    Some(prefix)
+                }
+                _ => None,
+            };
+
+            let mut counts = Counts::default();
+            for c in chars {
+                let count = c.to_digit(10).ok_or(RuleError::UnexpectedCharacter(c))?;
+                if count > 8 {
+                    return Err(RuleError::CountOutOfRange(count));
+                }
+                counts.insert(count);
+            }
+
+            Ok((prefix, counts))
+        }
+
+        let (first_prefix, first_counts) = parse_part(first)?;
+        let (second_prefix, second_counts) = parse_part(second)?;
+
+        let (birth, survival) = match (first_prefix, second_prefix) {
+            (Some('B'), Some('S')) => (first_counts, second_counts),
+            (Some('S'), Some('B')) => (second_counts, first_counts),
+            (Some(prefix), Some(_)) => return Err(RuleError::DuplicatePrefix(prefix)),
+            // The S/B notation lists the survival conditions first:
+            (None, None) => (second_counts, first_counts),
+            _ => return Err(RuleError::MixedNotation),
+        };
+
+        Rule {
+            birth,
+            survival,
+            neighborhood: Neighborhood::new(kind, 1),
+            includes_middle: false,
+            states,
+        }
+        .validate()
+    }
+}
+
+// The canonical notation of a rule is B/S with the neighbour counts
+// in ascending order. Rules with a larger range use the HROT notation
+// and rules that count the cell itself the Larger than Life notation.
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.neighborhood.kind() {
+            NeighborhoodKind::Moore => 'M',
+            NeighborhoodKind::VonNeumann => 'N',
+            NeighborhoodKind::Hexagonal => 'H',
+        };
+        // Rules that count the cell itself can only be written in
+        // the Larger than Life notation:
+        if self.includes_middle {
+            return write!(
+                f,
+                "R{},C{},M1,S{},B{},N{}",
+                self.neighborhood.range(),
+                if self.states == 2 { 0 } else { self.states },
+                self.survival.to_ranges(".."),
+                self.birth.to_ranges(".."),
+                kind
+            );
+        }
+        if self.neighborhood.range() > 1 {
+            return write!(
+                f,
+                "R{},C{},S{},B{},N{}",
+                self.neighborhood.range(),
+                self.states,
+                self.survival.to_ranges("-"),
+                self.birth.to_ranges("-"),
+                kind
+            );
+        }
+
+        write!(f, "B")?;
+        for count in self.birth.iter() {
+            write!(f, "{}", count)?;
+        }
+        write!(f, "/S")?;
+        for count in self.survival.iter() {
+            write!(f, "{}", count)?;
+        }
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+
+        match self.neighborhood.kind() {
+            NeighborhoodKind::Moore => Ok(()),
+            NeighborhoodKind::VonNeumann => write!(f, "V"),
+            NeighborhoodKind::Hexagonal => write!(f, "H"),
+        }
+    }
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleError::Empty => write!(f, "the rulestring is empty"),
+            RuleError::MissingSlash => {
+                write!(f, "the rulestring needs a '/' between birth and survival")
+            }
+            RuleError::MixedNotation => {
+                write!(f, "either both or none of the parts need a 'B'/'S' prefix")
+            }
+            RuleError::DuplicatePrefix(prefix) => {
+                write!(f, "the prefix '{}' was used for both parts", prefix)
+            }
+            RuleError::CountOutOfRange(count) => {
+                write!(f, "a cell can't have {} neighbours", count)
+            }
+            RuleError::UnexpectedCharacter(c) => {
+                write!(f, "unexpected character '{}' in rulestring", c)
+            }
+            RuleError::InvalidRange(range) => {
+                write!(f, "a neighbourhood can't have a range of {}", range)
+            }
+            RuleError::InvalidStates(states) => {
+                write!(f, "a rule can't have {} states", states)
+            }
+            RuleError::InvalidToken(token) => {
+                write!(f, "invalid part '{}' in rulestring", token)
+            }
+            RuleError::Unsupported(rule) => {
+                write!(f, "the rule {} isn't supported by this engine", rule)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+// Errors that are returned to JavaScript have to be converted
+// into a `JsValue`. We simply hand over the error message, which
+// is thrown as an exception on the JavaScript side.
+impl From<RuleError> for JsValue {
+    fn from(error: RuleError) -> JsValue {
+        JsValue::from_str(&error.to_string())
+    }
+}
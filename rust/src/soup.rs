@@ -0,0 +1,193 @@
+//! Random soups with enforced symmetries for soup searches.
+//!
+//! A soup is a small random pattern that is left to evolve until it
+//! settles, to see which objects it produces. Enforcing a symmetry on
+//! the soup makes rare symmetric objects far more likely to show up.
+//! The symmetries are named just like in apgsearch, so the results of
+//! both can be compared.
+use wasm_bindgen::prelude::*;
+
+use super::cell::Cell;
+use super::pattern::Pattern;
+use super::rng::SplitMix64;
+
+/// The side of the square of random cells that every soup is based on.
+pub const SOUP_SIZE: u32 = 16;
+
+#[wasm_bindgen]
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// The `Symmetry` that is enforced on a soup.
+///
+/// The letter and number name the symmetry group: `C` groups only
+/// rotate the soup, by 180° for `C2` and by 90° for `C4`, while `D`
+/// groups also mirror it, either along the orthogonal axes (`+`) or
+/// the diagonals (`x`). The number after the underscore tells where
+/// the center of the symmetry lies: on a cell (1), on the edge between
+/// two cells (2) or on the corner between four cells (4).
+///
+/// Soups with their center on a cell have an odd side, so that the
+/// center cell exists, and are one cell smaller than the 16x16 of
+/// the others.
+pub enum Symmetry {
+    /// No symmetry at all.
+    C1,
+    /// Rotation by 180° around a cell.
+    C2_1,
+    /// Rotation by 180° around the middle of an edge.
+    C2_2,
+    /// Rotation by 180° around a corner.
+    C2_4,
+    /// Rotation by 90° around a cell.
+    C4_1,
+    /// Rotation by 90° around a corner.
+    C4_4,
+    /// A mirror along a horizontal axis through a row of cells.
+    D2_Plus1,
+    /// A mirror along a horizontal axis between two rows of cells.
+    D2_Plus2,
+    /// A mirror along the diagonal.
+    D2_X,
+    /// Mirrors along both orthogonal axes, which cross on a cell.
+    D4_Plus1,
+    /// Mirrors along both orthogonal axes, which cross on an edge.
+    D4_Plus2,
+    /// Mirrors along both orthogonal axes, which cross on a corner.
+    D4_Plus4,
+    /// Mirrors along both diagonals, which cross on a cell.
+    D4_X1,
+    /// Mirrors along both diagonals, which cross on a corner.
+    D4_X4,
+    /// All rotations and mirrors around a cell.
+    D8_1,
+    /// All rotations and mirrors around a corner.
+    D8_4,
+}
+
+/// A transformation of the `(row, column)` of a cell
+/// within a soup of the given `width` and `height`.
+type Transform = fn(u32, u32, u32, u32) -> (u32, u32);
+
+const IDENTITY: Transform = |row, column, _, _| (row, column);
+const ROTATE_90: Transform = |row, column, width, _| (column, width - 1 - row);
+const ROTATE_180: Transform = |row, column, width, height| (height - 1 - row, width - 1 - column);
+const ROTATE_270: Transform = |row, column, _, height| (height - 1 - column, row);
+const FLIP_ROWS: Transform = |row, column, _, height| (height - 1 - row, column);
+const FLIP_COLUMNS: Transform = |row, column, width, _| (row, width - 1 - column);
+const TRANSPOSE: Transform = |row, column, _, _| (column, row);
+const ANTI_TRANSPOSE: Transform =
+    |row, column, width, height| (width - 1 - column, height - 1 - row);
+
+impl Symmetry {
+    /// Returns the name of the `Symmetry` as used by apgsearch,
+    /// e.g. `D2_+1` or `D4_x4`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Symmetry::C1 => "C1",
+            Symmetry::C2_1 => "C2_1",
+            Symmetry::C2_2 => "C2_2",
+            Symmetry::C2_4 => "C2_4",
+            Symmetry::C4_1 => "C4_1",
+            Symmetry::C4_4 => "C4_4",
+            Symmetry::D2_Plus1 => "D2_+1",
+            Symmetry::D2_Plus2 => "D2_+2",
+            Symmetry::D2_X => "D2_x",
+            Symmetry::D4_Plus1 => "D4_+1",
+            Symmetry::D4_Plus2 => "D4_+2",
+            Symmetry::D4_Plus4 => "D4_+4",
+            Symmetry::D4_X1 => "D4_x1",
+            Symmetry::D4_X4 => "D4_x4",
+            Symmetry::D8_1 => "D8_1",
+            Symmetry::D8_4 => "D8_4",
+        }
+    }
+
+    /// Returns the `(width, height)` of the soups of this `Symmetry`.
+    ///
+    /// A center on a cell needs an odd side and a center on a corner
+    /// an even one. A center on an edge lies on a cell along one axis
+    /// and between two cells along the other.
+    pub fn size(&self) -> (u32, u32) {
+        let (odd, even) = (SOUP_SIZE - 1, SOUP_SIZE);
+        match self {
+            Symmetry::C2_1
+            | Symmetry::C4_1
+            | Symmetry::D4_Plus1
+            | Symmetry::D4_X1
+            | Symmetry::D8_1 => (odd, odd),
+            Symmetry::C2_2 | Symmetry::D2_Plus1 | Symmetry::D4_Plus2 => (even, odd),
+            _ => (even, even),
+        }
+    }
+
+    /// Returns all transformations that leave a soup of
+    /// this `Symmetry` unchanged.
+    fn transforms(&self) -> &'static [Transform] {
+        match self {
+            Symmetry::C1 => &[IDENTITY],
+            Symmetry::C2_1 | Symmetry::C2_2 | Symmetry::C2_4 => &[IDENTITY, ROTATE_180],
+            Symmetry::C4_1 | Symmetry::C4_4 => &[IDENTITY, ROTATE_90, ROTATE_180, ROTATE_270],
+            Symmetry::D2_Plus1 | Symmetry::D2_Plus2 => &[IDENTITY, FLIP_ROWS],
+            Symmetry::D2_X => &[IDENTITY, TRANSPOSE],
+            Symmetry::D4_Plus1 | Symmetry::D4_Plus2 | Symmetry::D4_Plus4 => {
+                &[IDENTITY, FLIP_ROWS, FLIP_COLUMNS, ROTATE_180]
+            }
+            Symmetry::D4_X1 | Symmetry::D4_X4 => &[IDENTITY, TRANSPOSE, ANTI_TRANSPOSE, ROTATE_180],
+            Symmetry::D8_1 | Symmetry::D8_4 => &[
+                IDENTITY,
+                ROTATE_90,
+                ROTATE_180,
+                ROTATE_270,
+                FLIP_ROWS,
+                FLIP_COLUMNS,
+                TRANSPOSE,
+                ANTI_TRANSPOSE,
+            ],
+        }
+    }
+}
+
+/// Returns a random soup with the given `Symmetry`, in which
+/// each cell is alive with a probability of 50 %.
+///
+/// The same `seed` always gives the same soup.
+///
+/// # Algorithm explanation
+///
+/// The transformations of a `Symmetry` split the cells of a soup into
+/// groups of cells that are mapped onto each other, and each group has
+/// to share one state. We go through the cells in order and draw a
+/// random state only for the first cell of each group, i.e. the one
+/// with the smallest index. All other cells copy the state of the
+/// first cell of their group.
+pub fn generate(symmetry: Symmetry, seed: u64) -> Pattern {
+    let (width, height) = symmetry.size();
+    let mut rng = SplitMix64::new(seed);
+    let mut soup = Pattern::new(width, height);
+
+    for row in 0..height {
+        for column in 0..width {
+            let first = symmetry
+                .transforms()
+                .iter()
+                .map(|transform| transform(row, column, width, height))
+                .min()
+                .unwrap();
+
+            let cell = if first == (row, column) {
+                if rng.chance(0.5) {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
+                }
+            } else {
+                // The first cell of the group came before
+                // this one, so its state is already set:
+                soup.get(first.0, first.1)
+            };
+            soup.set(row, column, cell);
+        }
+    }
+
+    soup
+}
@@ -0,0 +1,392 @@
+use wasm_bindgen::prelude::*;
+// The tiles are looked up by their position on the plane:
+use std::collections::{HashMap, HashSet};
+
+use super::cell::Cell;
+use super::pattern::{Pattern, PatternError, MAX_CELLS};
+use super::rule::{Rule, RuleError};
+use super::universe::{Stats, Universe};
+
+/// The side of the square of cells that makes up a tile.
+const TILE_SIZE: i64 = 16;
+
+/// A square of `TILE_SIZE` x `TILE_SIZE` cells, stored as one bit
+/// row per row, where bit `n` of a row belongs to column `n`.
+type Tile = [u16; TILE_SIZE as usize];
+
+/// The position of a tile, i.e. the row and column of its
+/// top left cell divided by `TILE_SIZE`.
+type TilePosition = (i64, i64);
+
+#[wasm_bindgen]
+/// A `SparseUniverse` is an unbounded plane of cells that only stores
+/// the parts of the plane where cells are alive.
+///
+/// The plane is split into tiles of 16x16 cells, which are kept in a
+/// `HashMap` by their position. A tile is allocated as soon as one of
+/// its cells comes to life and dropped again once all of them are dead,
+/// so the memory grows with the pattern instead of the space it covers.
+/// Patterns never wrap around, so a glider flies on forever.
+///
+/// Just like in the `Universe`, cells are addressed by their `row`
+/// and `column`, which can be negative.
+pub struct SparseUniverse {
+    tiles: HashMap<TilePosition, Tile>,
+    rule: Rule,
+    generation: u32,
+    population: u32,
+    // The number of cells that were born or died during the last tick:
+    births: u32,
+    deaths: u32,
+}
+
+// These functions won't be exposed to JavaScript as
+// they're only meant to handle the internal state:
+impl SparseUniverse {
+    /// Returns the position of the tile of a cell and the
+    /// row and column of the cell within the tile.
+    fn locate(row: i64, column: i64) -> (TilePosition, usize, usize) {
+        (
+            (row.div_euclid(TILE_SIZE), column.div_euclid(TILE_SIZE)),
+            row.rem_euclid(TILE_SIZE) as usize,
+            column.rem_euclid(TILE_SIZE) as usize,
+        )
+    }
+
+    /// Returns a bit row of a tile, which is 0 for tiles that
+    /// aren't allocated.
+    fn tile_row(&self, position: TilePosition, row: usize) -> u32 {
+        self.tiles.get(&position).map_or(0, |tile| tile[row] as u32)
+    }
+
+    /// Returns the next generation of the tile at `position`.
+    ///
+    /// # Algorithm explanation
+    ///
+    /// We copy the cells of the tile together with a border of one
+    /// cell from the eight tiles around it into a window of 18 bit rows
+    /// of 18 bits each. Bit `n + 1` of row `r + 1` is the cell in row
+    /// `r` and column `n` of the tile. The 3x3 block around a cell is
+    /// then three bits of three rows, which we count all at once.
+    fn next_tile(&self, position: TilePosition) -> Tile {
+        let (tile_row, tile_column) = position;
+        let last = TILE_SIZE as usize - 1;
+
+        let mut window = [0u32; TILE_SIZE as usize + 2];
+        for (window_row, bits) in window.iter_mut().enumerate() {
+            // The rows above and below come from the neighbouring tiles:
+            let (row_tile, row) = match window_row {
+                0 => (tile_row - 1, last),
+                _ if window_row == last + 2 => (tile_row + 1, 0),
+                _ => (tile_row, window_row - 1),
+            };
+            let west = self.tile_row((row_tile, tile_column - 1), row) >> last & 1;
+            let center = self.tile_row((row_tile, tile_column), row);
+            let east = self.tile_row((row_tile, tile_column + 1), row) & 1;
+            *bits = west | center << 1 | east << (TILE_SIZE + 1);
+        }
+
+        let mut tile = [0; TILE_SIZE as usize];
+        for (row, bits) in tile.iter_mut().enumerate() {
+            for column in 0..TILE_SIZE as usize {
+                let block = |window_row: u32| (window_row >> column & 0b111).count_ones();
+                let cell = if window[row + 1] >> (column + 1) & 1 == 1 {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
+                };
+                let live_neighbors =
+                    block(window[row]) + block(window[row + 1]) + block(window[row + 2])
+                        - cell as u32;
+                if self.rule.next_cell(cell, live_neighbors) == Cell::Alive {
+                    *bits |= 1 << column;
+                }
+            }
+        }
+        tile
+    }
+
+    /// Inserts the living cells of a `Pattern` with its top
+    /// left corner at `row` and `column`.
+    ///
+    /// The dead cells of the `Pattern` are left as they are, so
+    /// patterns can be placed next to each other. Unlike for the
+    /// `Universe`, the `Rule` of the `Pattern` is ignored.
+    pub fn insert_pattern(&mut self, pattern: &Pattern, row: i64, column: i64) {
+        for (pattern_row, pattern_column) in pattern.live_cells() {
+            self.set_cell(
+                row + pattern_row as i64,
+                column + pattern_column as i64,
+                true,
+            );
+        }
+    }
+
+    /// Returns the smallest rectangle that contains all living cells.
+    ///
+    /// The rectangle is given as `(row, column, width, height)` of its
+    /// top left corner and its size, just like in the `Universe`. A
+    /// `SparseUniverse` without any living cells has no bounding box
+    /// and returns `None`.
+    pub fn bounding_box(&self) -> Option<(i64, i64, u32, u32)> {
+        let mut living = self
+            .tiles
+            .iter()
+            .flat_map(|(&(tile_row, tile_column), tile)| {
+                tile.iter().enumerate().flat_map(move |(row, &bits)| {
+                    (0..TILE_SIZE)
+                        .filter(move |&column| bits >> column & 1 == 1)
+                        .map(move |column| {
+                            (
+                                tile_row * TILE_SIZE + row as i64,
+                                tile_column * TILE_SIZE + column,
+                            )
+                        })
+                })
+            });
+
+        let (first_row, first_column) = living.next()?;
+        let (top, left, bottom, right) = living.fold(
+            (first_row, first_column, first_row, first_column),
+            |(top, left, bottom, right), (row, column)| {
+                (
+                    top.min(row),
+                    left.min(column),
+                    bottom.max(row),
+                    right.max(column),
+                )
+            },
+        );
+
+        Some((
+            top,
+            left,
+            (right - left + 1) as u32,
+            (bottom - top + 1) as u32,
+        ))
+    }
+}
+
+// JavaScript API:
+#[wasm_bindgen]
+impl SparseUniverse {
+    /// Creates an empty `SparseUniverse` that evolves
+    /// by Conway's Game of Life.
+    pub fn new() -> SparseUniverse {
+        super::utils::set_panic_hook();
+
+        SparseUniverse {
+            tiles: HashMap::new(),
+            rule: Rule::default(),
+            generation: 0,
+            population: 0,
+            births: 0,
+            deaths: 0,
+        }
+    }
+
+    /// Creates a `SparseUniverse` with the cells and `Rule` of an
+    /// `Universe`. The top left corner of the `Universe` ends up at
+    /// the origin.
+    ///
+    /// # Errors
+    /// The same rules as for `set_rule` are rejected.
+    pub fn from_universe(universe: &Universe) -> Result<SparseUniverse, RuleError> {
+        let mut sparse = SparseUniverse::new();
+        sparse.set_rule(&universe.rule())?;
+        for row in 0..universe.height() {
+            for column in 0..universe.width() {
+                if universe.get_cells()[universe.get_index(row, column)] == Cell::Alive {
+                    sparse.set_cell(row as i64, column as i64, true);
+                }
+            }
+        }
+        Ok(sparse)
+    }
+
+    /// Sets the `Rule` by which the universe evolves.
+    ///
+    /// The `rule` is a rulestring in the B/S notation. Rules with `B0`
+    /// would bring the whole unbounded plane to life and are rejected,
+    /// just like rules with another neighbourhood than the 8 cell one
+    /// or dying states.
+    pub fn set_rule(&mut self, rule: &str) -> Result<(), RuleError> {
+        let rule: Rule = rule.parse()?;
+        if rule.is_birth(0) || !rule.is_life_like() {
+            return Err(RuleError::Unsupported(rule.to_string()));
+        }
+        self.rule = rule;
+        Ok(())
+    }
+
+    /// Returns the current `Rule` as a rulestring in the B/S notation.
+    pub fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /// Sets the cell at `row` and `column` to alive or dead.
+    pub fn set_cell(&mut self, row: i64, column: i64, alive: bool) {
+        if self.is_alive(row, column) != alive {
+            self.toggle_cell(row, column);
+        }
+    }
+
+    /// Toggles the state of the cell at `row` and `column`.
+    pub fn toggle_cell(&mut self, row: i64, column: i64) {
+        let (position, row, column) = SparseUniverse::locate(row, column);
+        let tile = self
+            .tiles
+            .entry(position)
+            .or_insert([0; TILE_SIZE as usize]);
+        tile[row] ^= 1 << column;
+
+        if tile[row] >> column & 1 == 1 {
+            self.population += 1;
+        } else {
+            self.population -= 1;
+            // Tiles without living cells aren't kept around:
+            if tile.iter().all(|&bits| bits == 0) {
+                self.tiles.remove(&position);
+            }
+        }
+    }
+
+    /// Returns `true` if the cell at `row` and `column` is alive.
+    pub fn is_alive(&self, row: i64, column: i64) -> bool {
+        let (position, row, column) = SparseUniverse::locate(row, column);
+        self.tile_row(position, row) >> column & 1 == 1
+    }
+
+    /// Inserts a pattern in the RLE format with its top
+    /// left corner at `row` and `column`.
+    ///
+    /// Malformed patterns are thrown as errors on the JavaScript side.
+    pub fn insert_rle(&mut self, row: i64, column: i64, rle: &str) -> Result<(), PatternError> {
+        let pattern = super::rle::parse(rle)?;
+        self.insert_pattern(&pattern, row, column);
+        Ok(())
+    }
+
+    /// Advances the time by one tick.
+    ///
+    /// Only the allocated tiles and the tiles right next to them can
+    /// hold living cells in the next generation, so we compute those
+    /// and keep the ones that aren't empty.
+    pub fn tick(&mut self) {
+        let candidates: HashSet<TilePosition> = self
+            .tiles
+            .keys()
+            .flat_map(|&(tile_row, tile_column)| {
+                (-1..=1).flat_map(move |delta_row| {
+                    (-1..=1)
+                        .map(move |delta_column| (tile_row + delta_row, tile_column + delta_column))
+                })
+            })
+            .collect();
+
+        let (mut births, mut deaths) = (0, 0);
+        let mut tiles = HashMap::with_capacity(self.tiles.len());
+        for position in candidates {
+            let tile = self.next_tile(position);
+            for (row, &bits) in tile.iter().enumerate() {
+                let previous = self.tile_row(position, row);
+                births += (bits as u32 & !previous).count_ones();
+                deaths += (previous & !(bits as u32)).count_ones();
+            }
+            if tile.iter().any(|&bits| bits != 0) {
+                tiles.insert(position, tile);
+            }
+        }
+
+        self.tiles = tiles;
+        self.generation += 1;
+        self.population = self.population + births - deaths;
+        self.births = births;
+        self.deaths = deaths;
+    }
+
+    /// Advances the time by `n` ticks.
+    pub fn tick_n(&mut self, n: u32) {
+        for _ in 0..n {
+            self.tick();
+        }
+    }
+
+    /// Returns the cells of a window of the plane, e.g. the part that's
+    /// visible on the screen.
+    ///
+    /// The window starts with its top left corner at `row` and `column`
+    /// and spans `width` columns and `height` rows. The cells are returned
+    /// row by row with one byte per `Cell`, just like the cells of
+    /// the `Universe`.
+    ///
+    /// # Errors
+    /// Windows with more than `MAX_CELLS` cells are rejected with
+    /// `PatternError::TooLarge`, just like patterns.
+    pub fn viewport(
+        &self,
+        row: i64,
+        column: i64,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, PatternError> {
+        let size = (width as u64)
+            .checked_mul(height as u64)
+            .filter(|&size| size <= MAX_CELLS)
+            .ok_or(PatternError::TooLarge { width, height })?;
+
+        let mut cells = vec![Cell::Dead as u8; size as usize];
+        for delta_row in 0..height as i64 {
+            for delta_column in 0..width as i64 {
+                if self.is_alive(row + delta_row, column + delta_column) {
+                    cells[(delta_row * width as i64 + delta_column) as usize] = Cell::Alive as u8;
+                }
+            }
+        }
+        Ok(cells)
+    }
+
+    /// Returns the number of ticks since the
+    /// `SparseUniverse` was created.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Returns the number of living cells.
+    pub fn population(&self) -> u32 {
+        self.population
+    }
+
+    /// Returns the number of cells that were
+    /// born during the last tick.
+    pub fn births(&self) -> u32 {
+        self.births
+    }
+
+    /// Returns the number of cells that died
+    /// during the last tick.
+    pub fn deaths(&self) -> u32 {
+        self.deaths
+    }
+
+    /// Returns a snapshot of all statistics at once.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            generation: self.generation,
+            population: self.population,
+            births: self.births,
+            deaths: self.deaths,
+        }
+    }
+
+    /// Returns the number of tiles that are allocated.
+    pub fn tile_count(&self) -> u32 {
+        self.tiles.len() as u32
+    }
+}
+
+impl Default for SparseUniverse {
+    fn default() -> SparseUniverse {
+        SparseUniverse::new()
+    }
+}
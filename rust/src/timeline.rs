@@ -0,0 +1,255 @@
+//! A timeline of keyframes to step back to earlier generations.
+//!
+//! Life is irreversible, so there's no way to compute the generation
+//! before the current one. Instead, the timeline keeps a snapshot of
+//! the cells every couple of generations. To return to a generation, we
+//! restore the last snapshot before it and tick forward from there.
+use wasm_bindgen::prelude::*;
+// We need the std::fmt tools to print the errors:
+use std::fmt;
+use std::mem;
+
+use super::cell::Cell;
+use super::rule::Rule;
+use super::topology::Topology;
+
+/// The number of generations between two keyframes by default.
+pub const DEFAULT_INTERVAL: u32 = 32;
+
+/// The number of bytes a `Timeline` may use by default.
+///
+/// Every keyframe is a copy of all cells, so the timeline is
+/// turned off until a budget is set.
+pub const DEFAULT_BUDGET: usize = 0;
+
+/// A snapshot of everything that decides how a `Universe` evolves.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    pub generation: u32,
+    pub cells: Vec<Cell>,
+    /// The dying stages, if the `rule` has dying states.
+    pub dying: Option<Vec<u8>>,
+    pub rule: Rule,
+    pub topology: Topology,
+    /// Whether the keyframe was taken after an edit, which
+    /// can't be computed from the keyframes before it.
+    pub edited: bool,
+}
+
+impl Keyframe {
+    /// Returns the number of bytes the `Keyframe` takes up, roughly.
+    pub fn size(&self) -> usize {
+        mem::size_of::<Keyframe>()
+            + self.cells.len() * mem::size_of::<Cell>()
+            + self.dying.as_ref().map_or(0, Vec::len)
+    }
+}
+
+/// The errors that can occur while seeking a generation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TimelineError {
+    /// The generation wasn't visited since the last edit, or the
+    /// `Timeline` already forgot it.
+    NotVisited { generation: u32 },
+}
+
+impl fmt::Display for TimelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimelineError::NotVisited { generation } => write!(
+                f,
+                "generation {} isn't on the timeline of the universe",
+                generation
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TimelineError {}
+
+// Errors are thrown as exceptions on the JavaScript side:
+impl From<TimelineError> for JsValue {
+    fn from(error: TimelineError) -> JsValue {
+        JsValue::from_str(&error.to_string())
+    }
+}
+
+/// A `Timeline` keeps keyframes of a run, sorted by their generation.
+///
+/// A keyframe is taken every `interval` generations and after every
+/// edit. An edit changes the course of the run, so all keyframes after
+/// it are forgotten. The generations from the first keyframe up to the
+/// `latest` one that was reached can be recomputed.
+///
+/// # Algorithm explanation
+///
+/// The keyframes never take up more than the `budget`. When they run
+/// out of space, every other periodic keyframe is dropped and the
+/// `interval` doubles, so that a long run keeps the same number of
+/// keyframes spread over all of it. Keyframes after edits can't be
+/// dropped this way, because the generations after them couldn't be
+/// recomputed anymore. If there are no periodic keyframes left to drop,
+/// the oldest keyframes are forgotten and the timeline gets shorter.
+#[derive(Clone, Debug)]
+pub struct Timeline {
+    interval: u32,
+    budget: usize,
+    // The number of bytes all keyframes take up together:
+    used: usize,
+    keyframes: Vec<Keyframe>,
+    // The last generation that can be recomputed:
+    latest: u32,
+}
+
+impl Timeline {
+    /// Creates an empty `Timeline` that takes a keyframe every
+    /// `interval` generations and uses up to `budget` bytes.
+    ///
+    /// # Panics
+    /// The `interval` has to be at least 1.
+    pub fn new(interval: u32, budget: usize) -> Timeline {
+        assert!(interval > 0, "the interval between keyframes can't be 0");
+        Timeline {
+            interval,
+            budget,
+            used: 0,
+            keyframes: Vec::new(),
+            latest: 0,
+        }
+    }
+
+    /// Returns the number of generations between two keyframes.
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    /// Returns the number of bytes the `Timeline` may use.
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Changes the number of bytes the `Timeline` may use and forgets
+    /// keyframes until they fit. A `budget` of 0 turns it off.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.trim();
+    }
+
+    /// Returns the first generation that can be recomputed.
+    pub fn earliest(&self) -> Option<u32> {
+        self.keyframes.first().map(|keyframe| keyframe.generation)
+    }
+
+    /// Returns the last generation that can be recomputed.
+    pub fn latest(&self) -> Option<u32> {
+        self.earliest().map(|_| self.latest)
+    }
+
+    /// Returns `true` if a keyframe should be taken in the `generation`
+    /// that was reached by a tick.
+    pub fn wants(&self, generation: u32) -> bool {
+        self.budget > 0
+            && generation.is_multiple_of(self.interval)
+            && self.find(generation).is_err()
+    }
+
+    /// Notes that the `generation` was reached by a tick, which
+    /// makes it possible to recompute it.
+    pub fn reach(&mut self, generation: u32) {
+        if !self.keyframes.is_empty() {
+            self.latest = self.latest.max(generation);
+        }
+    }
+
+    /// Records a keyframe that was taken after a tick.
+    pub fn record(&mut self, keyframe: Keyframe) {
+        let generation = keyframe.generation;
+        self.insert(keyframe);
+        self.reach(generation);
+    }
+
+    /// Records a keyframe that was taken after an edit and forgets
+    /// all keyframes after it, including an earlier one of the
+    /// same generation.
+    pub fn record_edit(&mut self, keyframe: Keyframe) {
+        while let Some(last) = self.keyframes.last() {
+            if last.generation < keyframe.generation {
+                break;
+            }
+            self.used -= last.size();
+            self.keyframes.pop();
+        }
+        self.latest = keyframe.generation;
+        self.insert(keyframe);
+    }
+
+    /// Returns the last keyframe at or before the `generation`,
+    /// if the `generation` can be recomputed from it.
+    pub fn keyframe_before(&self, generation: u32) -> Option<&Keyframe> {
+        if generation > self.latest {
+            return None;
+        }
+        let idx = match self.find(generation) {
+            Ok(idx) => idx,
+            Err(0) => return None,
+            Err(idx) => idx - 1,
+        };
+        Some(&self.keyframes[idx])
+    }
+
+    /// Forgets all keyframes.
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+        self.used = 0;
+        self.latest = 0;
+    }
+
+    /// Looks up the index of the keyframe of the `generation`, or
+    /// the index where it would have to be inserted.
+    fn find(&self, generation: u32) -> Result<usize, usize> {
+        self.keyframes
+            .binary_search_by_key(&generation, |keyframe| keyframe.generation)
+    }
+
+    /// Inserts a keyframe at its place and makes room for it.
+    fn insert(&mut self, keyframe: Keyframe) {
+        if keyframe.size() > self.budget {
+            self.clear();
+            return;
+        }
+        self.used += keyframe.size();
+        match self.find(keyframe.generation) {
+            Ok(idx) => {
+                self.used -= self.keyframes[idx].size();
+                self.keyframes[idx] = keyframe;
+            }
+            Err(idx) => self.keyframes.insert(idx, keyframe),
+        }
+        self.trim();
+    }
+
+    /// Drops keyframes until they fit into the `budget`,
+    /// see the algorithm explanation of the `Timeline`.
+    fn trim(&mut self) {
+        while self.used > self.budget {
+            let interval = self.interval.saturating_mul(2);
+            let droppable = |keyframe: &Keyframe| {
+                !keyframe.edited && !keyframe.generation.is_multiple_of(interval)
+            };
+
+            if self.keyframes.iter().any(droppable) {
+                let (dropped, kept): (Vec<Keyframe>, Vec<Keyframe>) =
+                    self.keyframes.drain(..).partition(droppable);
+                self.used -= dropped.iter().map(Keyframe::size).sum::<usize>();
+                self.keyframes = kept;
+                self.interval = interval;
+            } else {
+                let first = self.keyframes.remove(0);
+                self.used -= first.size();
+                if self.keyframes.is_empty() {
+                    self.latest = 0;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The `Topology` determines what lies beyond the edges of a `Universe`.
+///
+/// Each topology glues the edges of the grid together in a different
+/// way, or not at all. A cell that would end up beyond an edge that
+/// isn't glued to another one is outside of the `Universe` and
+/// always counts as dead.
+pub enum Topology {
+    /// The left edge is glued to the right one and the top edge to the
+    /// bottom one. This is the classic wrapping grid.
+    Torus,
+    /// No edges are glued together, the grid is surrounded by dead cells.
+    Plane,
+    /// Like a torus, but crossing the top or bottom edge mirrors the
+    /// column, so a glider leaving at the top left re-enters at the
+    /// bottom right.
+    KleinBottle,
+    /// Crossing the top or bottom edge mirrors the column and crossing
+    /// the left or right edge mirrors the row. This is also known as
+    /// the real projective plane.
+    CrossSurface,
+    /// The left edge is glued to the right one, while the top and the
+    /// bottom are bounded by dead cells.
+    HorizontalCylinder,
+    /// The top edge is glued to the bottom one, while the left and the
+    /// right are bounded by dead cells.
+    VerticalCylinder,
+}
+
+impl Topology {
+    /// Returns the `(row, column)` of the cell that sits at the given
+    /// position on a grid of `width` and `height`, where the position
+    /// may lie beyond the edges of the grid.
+    ///
+    /// # Algorithm explanation
+    ///
+    /// We count how many times the position crosses the edges in each
+    /// direction. Each crossing of a glued edge brings us back to the
+    /// opposite side of the grid, and each crossing of a twisted edge
+    /// additionally mirrors the other coordinate. If the position
+    /// crosses an edge that isn't glued, the cell is outside of the
+    /// grid and we return `None`.
+    pub fn resolve(&self, row: i64, column: i64, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (width, height) = (width as i64, height as i64);
+        let vertical_crossings = row.div_euclid(height);
+        let horizontal_crossings = column.div_euclid(width);
+        let mut wrapped_row = row.rem_euclid(height);
+        let mut wrapped_column = column.rem_euclid(width);
+
+        if (vertical_crossings != 0 && !self.wraps_vertically())
+            || (horizontal_crossings != 0 && !self.wraps_horizontally())
+        {
+            return None;
+        }
+
+        // An odd number of crossings of a twisted edge mirrors:
+        let twists_vertically = matches!(self, Topology::KleinBottle | Topology::CrossSurface);
+        let twists_horizontally = matches!(self, Topology::CrossSurface);
+        if twists_vertically && vertical_crossings % 2 != 0 {
+            wrapped_column = width - 1 - wrapped_column;
+        }
+        if twists_horizontally && horizontal_crossings % 2 != 0 {
+            wrapped_row = height - 1 - wrapped_row;
+        }
+
+        Some((wrapped_row as u32, wrapped_column as u32))
+    }
+
+    /// Returns `true` if the top edge is glued to the bottom one, so
+    /// that the last row is followed by the first one.
+    ///
+    /// On a hexagonal grid, every odd row is shifted to the right. With
+    /// an odd height, the last and the first row would both be unshifted
+    /// and the cells across the glued edge wouldn't be adjacent anymore,
+    /// which is why hexagonal rules need an even height here.
+    pub fn wraps_vertically(&self) -> bool {
+        matches!(
+            self,
+            Topology::Torus
+                | Topology::KleinBottle
+                | Topology::CrossSurface
+                | Topology::VerticalCylinder
+        )
+    }
+
+    /// Returns `true` if the left edge is glued to the right one.
+    pub fn wraps_horizontally(&self) -> bool {
+        matches!(
+            self,
+            Topology::Torus
+                | Topology::KleinBottle
+                | Topology::CrossSurface
+                | Topology::HorizontalCylinder
+        )
+    }
+}
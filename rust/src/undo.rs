@@ -0,0 +1,177 @@
+//! An undo history for the edits and ticks of a `Universe`.
+//!
+//! Every edit and every tick is recorded as a `Step` that only holds the
+//! cells it changed, together with their state before and after. A tick
+//! of a settled universe or a toggled cell costs a couple of bytes, no
+//! matter how large the universe is.
+use std::collections::VecDeque;
+use std::mem;
+
+use super::cell::Cell;
+use super::rule::Rule;
+use super::topology::Topology;
+
+/// The number of bytes an `UndoHistory` may use by default.
+///
+/// Recording a tick means comparing all cells with the previous
+/// generation, which isn't free, so the undo history is turned off
+/// until a budget is set.
+pub const DEFAULT_BUDGET: usize = 0;
+
+/// The state of a single cell: the `Cell` itself and its dying stage.
+pub type CellState = (Cell, u8);
+
+/// A single cell that was changed by a `Step`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CellChange {
+    pub index: usize,
+    pub before: CellState,
+    pub after: CellState,
+}
+
+/// A reversible change to a `Universe`, i.e. an edit or a tick.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    /// The cells that changed, in the order of their index.
+    pub changes: Vec<CellChange>,
+    /// The generation before and after the `Step`.
+    pub generations: (u32, u32),
+    /// The `Rule` before and after the `Step`, if it changed.
+    pub rules: Option<(Rule, Rule)>,
+    /// The `Topology` before and after the `Step`, if it changed.
+    pub topologies: Option<(Topology, Topology)>,
+}
+
+impl Step {
+    /// Returns `true` if the `Step` doesn't change anything.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+            && self.generations.0 == self.generations.1
+            && self.rules.is_none()
+            && self.topologies.is_none()
+    }
+
+    /// Returns the number of bytes the `Step` takes up, roughly.
+    pub fn size(&self) -> usize {
+        mem::size_of::<Step>() + self.changes.len() * mem::size_of::<CellChange>()
+    }
+}
+
+/// An `UndoHistory` keeps the steps that can be undone and
+/// the ones that were undone and can be redone.
+///
+/// Together, the steps never take up more than the `budget`. When a new
+/// `Step` doesn't fit anymore, the oldest steps are forgotten first. A
+/// `Step` that's larger than the whole `budget` can't be undone at all,
+/// so it forgets all steps before it as well.
+#[derive(Clone, Debug)]
+pub struct UndoHistory {
+    budget: usize,
+    // The number of bytes all steps take up together:
+    used: usize,
+    // The steps to undo, with the latest one at the back:
+    undo: VecDeque<Step>,
+    // The steps to redo, with the latest undone one at the back:
+    redo: VecDeque<Step>,
+}
+
+impl UndoHistory {
+    /// Creates an empty `UndoHistory` that uses up to `budget` bytes.
+    pub fn new(budget: usize) -> UndoHistory {
+        UndoHistory {
+            budget,
+            used: 0,
+            undo: VecDeque::new(),
+            redo: VecDeque::new(),
+        }
+    }
+
+    /// Returns the number of bytes the `UndoHistory` may use.
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Changes the number of bytes the `UndoHistory` may use and forgets
+    /// the oldest steps until they fit. A `budget` of 0 turns it off.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.trim();
+    }
+
+    /// Records a new `Step`, which can't be combined with
+    /// the undone steps anymore, so they're forgotten.
+    pub fn record(&mut self, step: Step) {
+        if step.is_empty() {
+            return;
+        }
+        for undone in self.redo.drain(..) {
+            self.used -= undone.size();
+        }
+        if step.size() > self.budget {
+            self.clear();
+            return;
+        }
+        self.push_undo(step);
+    }
+
+    /// Takes the latest `Step` that can be undone.
+    pub fn pop_undo(&mut self) -> Option<Step> {
+        let step = self.undo.pop_back()?;
+        self.used -= step.size();
+        Some(step)
+    }
+
+    /// Takes the latest undone `Step`, which can be redone.
+    pub fn pop_redo(&mut self) -> Option<Step> {
+        let step = self.redo.pop_back()?;
+        self.used -= step.size();
+        Some(step)
+    }
+
+    /// Puts a `Step` that was redone back on the steps to undo,
+    /// without forgetting the other undone steps.
+    pub fn push_undo(&mut self, step: Step) {
+        self.used += step.size();
+        self.undo.push_back(step);
+        self.trim();
+    }
+
+    /// Puts a `Step` that was undone on the steps to redo.
+    pub fn push_redo(&mut self, step: Step) {
+        self.used += step.size();
+        self.redo.push_back(step);
+        self.trim();
+    }
+
+    /// Returns `true` if there's a `Step` to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Returns `true` if there's a `Step` to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forgets all steps.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.used = 0;
+    }
+
+    /// Forgets the oldest steps to undo until all steps fit into the
+    /// `budget`, and then the undone steps that are furthest away.
+    fn trim(&mut self) {
+        while self.used > self.budget {
+            let step = match self.undo.pop_front() {
+                Some(step) => step,
+                None => match self.redo.pop_front() {
+                    Some(step) => step,
+                    None => break,
+                },
+            };
+            self.used -= step.size();
+        }
+    }
+}
@@ -2,6 +2,9 @@ use wasm_bindgen::prelude::*;
 // We need the std::fmt tools to print
 // game state to the terminal:
 use std::fmt;
+// We need hashing to recognize states we've seen before:
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
 // Crate to generate random numbers:
 use rand::Rng;
 
@@ -13,18 +16,90 @@ pub enum UniverseOption {
     Dead,
 }
 
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The point of an `Universe` that stays in place when it's resized.
+pub enum Anchor {
+    TopLeft,
+    TopRight,
+    Center,
+    BottomLeft,
+    BottomRight,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A `StopCondition` ends a `run_until` before it
+/// reaches its maximum number of generations.
+pub enum StopCondition {
+    /// Stop once no living cell is left.
+    Extinct,
+    /// Stop once the cells are in a state they were in
+    /// before during the run.
+    Repeats,
+    /// Stop once there are more living cells than a threshold.
+    PopulationExceeds,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A snapshot of the statistics of an `Universe`.
+///
+/// `births` and `deaths` count the cells that came to life or
+/// died during the last tick.
+pub struct Stats {
+    pub generation: u32,
+    pub population: u32,
+    pub births: u32,
+    pub deaths: u32,
+}
+
 #[wasm_bindgen]
 /// The `Universe` stores a collection of `Cell` instances.
 ///
 /// Each `Universe` is defined by a `width` and `height`,
 /// which make up the grid and possible spots for all
 /// `Cell` instances.
-#[derive(PartialEq)]
 pub struct Universe {
     width: u32,
     height: u32,
     cells: Vec<super::cell::Cell>,
+    // The buffer that `tick` writes the next generation into. It's
+    // swapped with `cells` after each tick, so that both buffers are
+    // reused instead of allocating a new one for every generation.
+    next: Vec<super::cell::Cell>,
+    // The dying stage of each cell for rules with more than two states,
+    // 0 for living and dead cells. Dying cells are `Cell::Dead` in
+    // `cells`, so that they don't count as living neighbours. Two-state
+    // rules have no dying stages, so both buffers are only allocated
+    // for rules with dying states, see `fit_dying`:
+    dying: Option<Vec<u8>>,
+    next_dying: Option<Vec<u8>>,
+    // The number of ticks each cell has been in its current state, if
+    // age tracking is turned on. The ages only depend on the cell itself,
+    // so unlike the cells they can be updated in place:
+    ages: Option<Vec<u16>>,
     universe_option: UniverseOption,
+    rule: super::rule::Rule,
+    // The number of ticks since the creation of the universe:
+    generation: u32,
+    // The number of living cells, which we keep up to date with every
+    // change, so that nobody has to scan all cells to get it:
+    population: u32,
+    // The number of cells that were born or died during the last tick:
+    births: u32,
+    deaths: u32,
+    // The hashes of the last states, to detect when
+    // the universe starts repeating itself:
+    states: super::cycle::StateHistory,
+    topology: super::topology::Topology,
+    // The cells of the last region that was copied or cut, which
+    // `paste` merges back into the universe:
+    clipboard: Option<super::pattern::Pattern>,
+    // The edits and ticks that can be undone and redone:
+    undo: super::undo::UndoHistory,
+    // The keyframes to return to earlier generations:
+    timeline: super::timeline::Timeline,
 }
 
 // These functions won't be exposed to JavaScript as
@@ -74,6 +149,591 @@ impl Universe {
         &self.cells
     }
 
+    /// Returns the dying stages of all cells, which are 0 for living
+    /// and dead cells, if the `Rule` has dying states.
+    pub fn get_dying_cells(&self) -> Option<&Vec<u8>> {
+        self.dying.as_ref()
+    }
+
+    /// Returns the ages of all cells, if age tracking is turned on.
+    pub fn get_ages(&self) -> Option<&Vec<u16>> {
+        self.ages.as_ref()
+    }
+
+    /// Inserts a `Pattern` with its top left corner at
+    /// the given `row` and `column`.
+    ///
+    /// The whole rectangle of the `Pattern` is copied into the
+    /// `Universe`, so dead cells of the `Pattern` overwrite living ones.
+    /// Cells that would end up beyond an edge of the `Universe` are
+    /// placed according to its `Topology`: they wrap around glued edges
+    /// and are dropped beyond bounded ones. If the `Pattern` was made
+    /// for a specific `Rule`, the `Universe` switches to it.
+    ///
+    /// # Errors
+    /// A `Pattern` that is wider or higher than the `Universe` would
+    /// overlap itself and is rejected with `PatternError::TooLarge`.
+    /// A `Rule` that can't run on the `Universe`, see `check_rule`, is
+    /// rejected with `PatternError::InvalidRule`.
+    pub fn insert_pattern(
+        &mut self,
+        pattern: &super::pattern::Pattern,
+        row: u32,
+        column: u32,
+    ) -> Result<(), super::pattern::PatternError> {
+        self.insert_states(pattern, None, row, column)
+    }
+
+    /// Brings all cells at the given `(row, column)` positions to life
+    /// in a single edit.
+    ///
+    /// Unlike toggling them one by one, the undo step, the history of
+    /// states and the timeline only see one change, so the engines that
+    /// convert their cells into an `Universe` use this.
+    pub fn set_cells(&mut self, cells: &[(u32, u32)]) {
+        let before = self.begin_edit();
+        for &(row, column) in cells {
+            let idx = self.get_index(row, column);
+            self.write_cell(idx, super::cell::Cell::Alive);
+        }
+        self.population = self.live_cell_count();
+        self.finish_edit(before);
+    }
+
+    /// Inserts a `Pattern` just like `insert_pattern` and then gives
+    /// its dying cells their stages, if there are `states`.
+    ///
+    /// The `states` hold one state per cell of the `Pattern`, as
+    /// `rle::parse_states_within` returns them. States the `Rule` of the
+    /// universe doesn't have leave their cells dead.
+    pub fn insert_states(
+        &mut self,
+        pattern: &super::pattern::Pattern,
+        states: Option<&[u8]>,
+        row: u32,
+        column: u32,
+    ) -> Result<(), super::pattern::PatternError> {
+        let before = self.begin_edit();
+        self.check_fits(pattern.width(), pattern.height())?;
+        if let Some(rule) = pattern.rule() {
+            Universe::check_rule(rule, self.topology, self.height)
+                .map_err(super::pattern::PatternError::InvalidRule)?;
+        }
+        self.write_pattern(
+            pattern,
+            row as i64,
+            column as i64,
+            super::pattern::MergeMode::Overwrite,
+        );
+
+        if let Some(rule) = pattern.rule() {
+            self.adopt_rule(rule.clone());
+        }
+
+        // The stages of a new `Rule` with dying states only exist once
+        // they're allocated, while the ones before the edit are kept:
+        if self.rule.states() > 2 {
+            self.fit_dying();
+        }
+        for (pattern_idx, &state) in states.unwrap_or_default().iter().enumerate() {
+            if state < 2 || state >= self.rule.states() {
+                continue;
+            }
+            let pattern_row = (pattern_idx / pattern.width() as usize) as i64;
+            let pattern_column = (pattern_idx % pattern.width() as usize) as i64;
+            if let Some(idx) =
+                self.resolve(row as i64 + pattern_row, column as i64 + pattern_column)
+            {
+                self.set_dying_stage(idx, state - 1);
+            }
+        }
+        self.finish_edit(before);
+
+        Ok(())
+    }
+
+    /// Checks that a `Rule` can run on a `Universe` of the given
+    /// `height` and `Topology`.
+    ///
+    /// Hexagonal neighbourhoods shift every odd row, so they need an
+    /// even number of rows on topologies that glue the last row to the
+    /// first one, see `Topology::wraps_vertically`. Otherwise the rule
+    /// is rejected with `RuleError::Unsupported`.
+    fn check_rule(
+        rule: &super::rule::Rule,
+        topology: super::topology::Topology,
+        height: u32,
+    ) -> Result<(), super::rule::RuleError> {
+        if rule.neighborhood().kind() == super::neighborhood::NeighborhoodKind::Hexagonal
+            && topology.wraps_vertically()
+            && !height.is_multiple_of(2)
+        {
+            return Err(super::rule::RuleError::Unsupported(rule.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Checks that a rectangle of `width` and `height` fits into the
+    /// `Universe` without overlapping itself.
+    fn check_fits(&self, width: u32, height: u32) -> Result<(), super::pattern::PatternError> {
+        if width > self.width || height > self.height {
+            return Err(super::pattern::PatternError::TooLarge { width, height });
+        }
+        Ok(())
+    }
+
+    /// Merges the cells of a `Pattern` into the `Universe` with its top
+    /// left corner at the given position, which may lie beyond the edges.
+    ///
+    /// Unlike `insert_pattern`, neither the size nor the `Rule` of the
+    /// `Pattern` are checked and the history isn't restarted.
+    fn write_pattern(
+        &mut self,
+        pattern: &super::pattern::Pattern,
+        row: i64,
+        column: i64,
+        mode: super::pattern::MergeMode,
+    ) {
+        for pattern_row in 0..pattern.height() {
+            for pattern_column in 0..pattern.width() {
+                if let Some(idx) =
+                    self.resolve(row + pattern_row as i64, column + pattern_column as i64)
+                {
+                    let cell =
+                        mode.merge(self.cells[idx], pattern.get(pattern_row, pattern_column));
+                    self.write_cell(idx, cell);
+                }
+            }
+        }
+        self.population = self.live_cell_count();
+    }
+
+    /// Sets all cells of a rectangular region to the same `Cell`.
+    ///
+    /// Just like `write_pattern`, the region continues beyond the edges
+    /// according to the `Topology` and the history isn't restarted.
+    fn write_region(
+        &mut self,
+        row: u32,
+        column: u32,
+        width: u32,
+        height: u32,
+        cell: super::cell::Cell,
+    ) {
+        for region_row in 0..height {
+            for region_column in 0..width {
+                if let Some(idx) = self.resolve(
+                    row as i64 + region_row as i64,
+                    column as i64 + region_column as i64,
+                ) {
+                    self.write_cell(idx, cell);
+                }
+            }
+        }
+        self.population = self.live_cell_count();
+    }
+
+    /// Sets the `Cell` at the given index, which also ends its dying
+    /// stages and restarts its age if it changes.
+    ///
+    /// The `population` has to be recounted afterwards.
+    fn write_cell(&mut self, idx: usize, cell: super::cell::Cell) {
+        if cell != self.cells[idx] {
+            self.reset_age(idx);
+        }
+        self.cells[idx] = cell;
+        self.set_dying_stage(idx, 0);
+    }
+
+    /// Returns the smallest rectangle that contains all living cells.
+    ///
+    /// The rectangle is given as `(row, column, width, height)` of its
+    /// top left corner and its size. An `Universe` without any living
+    /// cells has no bounding box and returns `None`.
+    pub fn bounding_box(&self) -> Option<(u32, u32, u32, u32)> {
+        self.bounding_box_of(|idx| self.cells[idx] == super::cell::Cell::Alive)
+    }
+
+    /// Returns the smallest rectangle that contains all cells
+    /// for whose index `occupied` returns `true`.
+    fn bounding_box_of(&self, occupied: impl Fn(usize) -> bool) -> Option<(u32, u32, u32, u32)> {
+        let mut positions = (0..self.cells.len())
+            .filter(|&idx| occupied(idx))
+            .map(|idx| (idx as u32 / self.width, idx as u32 % self.width));
+
+        let (first_row, first_column) = positions.next()?;
+        let (top, left, bottom, right) = positions.fold(
+            (first_row, first_column, first_row, first_column),
+            |(top, left, bottom, right), (row, column)| {
+                (
+                    top.min(row),
+                    left.min(column),
+                    bottom.max(row),
+                    right.max(column),
+                )
+            },
+        );
+
+        Some((top, left, right - left + 1, bottom - top + 1))
+    }
+
+    /// Returns the cells of a rectangular region as a `Pattern`.
+    ///
+    /// The region starts with its top left corner at `row` and `column`.
+    /// It continues beyond the edges of the `Universe` according to its
+    /// `Topology`, where cells outside of the `Universe` are dead.
+    pub fn region(
+        &self,
+        row: u32,
+        column: u32,
+        width: u32,
+        height: u32,
+    ) -> super::pattern::Pattern {
+        let mut pattern = super::pattern::Pattern::new(width, height);
+        for pattern_row in 0..height {
+            for pattern_column in 0..width {
+                if let Some(idx) = self.resolve(
+                    row as i64 + pattern_row as i64,
+                    column as i64 + pattern_column as i64,
+                ) {
+                    pattern.set(pattern_row, pattern_column, self.cells[idx]);
+                }
+            }
+        }
+        pattern.set_rule(Some(self.rule.clone()));
+        pattern
+    }
+
+    /// Returns all living cells of the `Universe` as a `Pattern`.
+    ///
+    /// The `Pattern` is cropped to the bounding box of the living
+    /// cells and carries the current `Rule` of the `Universe`.
+    pub fn to_pattern(&self) -> super::pattern::Pattern {
+        let (row, column, width, height) = self.bounding_box().unwrap_or((0, 0, 0, 0));
+        self.region(row, column, width, height)
+    }
+
+    /// Returns a hash of the state of all cells.
+    ///
+    /// Two universes with the same cells have the same hash, so we can
+    /// recognize a state we've seen before without keeping all of its
+    /// cells around. The hash is computed with the fast `StateHasher`,
+    /// as it's taken after every tick.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = super::cycle::StateHasher::default();
+        self.cells.hash(&mut hasher);
+        self.dying.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Counts the living cells one by one.
+    ///
+    /// This is only needed after changes to many cells at once,
+    /// otherwise the `population` is kept up to date on the fly.
+    fn live_cell_count(&self) -> u32 {
+        self.cells
+            .iter()
+            .filter(|&&cell| cell == super::cell::Cell::Alive)
+            .count() as u32
+    }
+
+    /// Returns the index of the `Cell` at the given position, which
+    /// may lie beyond the edges of the `Universe`.
+    ///
+    /// The position is resolved according to the `Topology` of the
+    /// `Universe`. Positions outside of it return `None`.
+    pub fn resolve(&self, row: i64, column: i64) -> Option<usize> {
+        self.topology
+            .resolve(row, column, self.width, self.height)
+            .map(|(row, column)| self.get_index(row, column))
+    }
+
+    /// Forgets the previous states of the `Universe` and starts
+    /// over from the current one.
+    ///
+    /// This is needed after every change to the cells that doesn't
+    /// come from a tick, as it breaks the chain of generations.
+    fn restart_history(&mut self) {
+        self.states.clear();
+        self.record_state();
+    }
+
+    /// Records the current state in the history of states, if it
+    /// remembers any, so that a `capacity` of 0 skips the hashing.
+    fn record_state(&mut self) {
+        if self.states.is_enabled() {
+            self.states.record(self.state_hash(), self.generation);
+        }
+    }
+
+    /// Prepares an edit that can be undone.
+    ///
+    /// The cells before the edit are copied into the buffers for the
+    /// next generation, which are only scratch space between ticks, so
+    /// that `finish_edit` can find the changed cells. Returns the `Rule`
+    /// and `Topology` before the edit, which have to be passed on to
+    /// `finish_edit`.
+    fn begin_edit(&mut self) -> (super::rule::Rule, super::topology::Topology) {
+        if self.undo.budget() > 0 {
+            self.next.copy_from_slice(&self.cells);
+            if let (Some(next_dying), Some(dying)) = (self.next_dying.as_mut(), self.dying.as_ref())
+            {
+                next_dying.copy_from_slice(dying);
+            }
+        }
+        (self.rule.clone(), self.topology)
+    }
+
+    /// Records the edit since `begin_edit` in the undo history
+    /// and restarts the history of states.
+    ///
+    /// The dying stages are only allocated or dropped for a new `Rule`
+    /// here, once the stages before the edit aren't needed anymore.
+    fn finish_edit(&mut self, (rule, topology): (super::rule::Rule, super::topology::Topology)) {
+        if self.undo.budget() > 0 {
+            let rules = if rule != self.rule {
+                Some((rule, self.rule.clone()))
+            } else {
+                None
+            };
+            let topologies = if topology != self.topology {
+                Some((topology, self.topology))
+            } else {
+                None
+            };
+            self.undo.record(super::undo::Step {
+                changes: self.changes(&self.next, self.next_dying.as_deref()),
+                generations: (self.generation, self.generation),
+                rules,
+                topologies,
+            });
+        }
+        self.fit_dying();
+        self.restart_history();
+        self.record_edit_keyframe();
+    }
+
+    /// Returns all cells that differ between the given cells and dying
+    /// stages before an edit or tick and the current ones. Without dying
+    /// stages, all stages are 0.
+    fn changes(
+        &self,
+        cells: &[super::cell::Cell],
+        dying: Option<&[u8]>,
+    ) -> Vec<super::undo::CellChange> {
+        let stage = |idx: usize| dying.map_or(0, |dying| dying[idx]);
+        (0..self.cells.len())
+            .filter(|&idx| self.cells[idx] != cells[idx] || self.dying_stage(idx) != stage(idx))
+            .map(|idx| super::undo::CellChange {
+                index: idx,
+                before: (cells[idx], stage(idx)),
+                after: (self.cells[idx], self.dying_stage(idx)),
+            })
+            .collect()
+    }
+
+    /// Returns the dying stage of the `Cell` at the given index,
+    /// which is 0 for all cells under rules without dying states.
+    fn dying_stage(&self, idx: usize) -> u8 {
+        self.dying.as_ref().map_or(0, |dying| dying[idx])
+    }
+
+    /// Sets the dying stage of the `Cell` at the given index. Rules
+    /// without dying states only have stage 0, so there's nothing to set.
+    fn set_dying_stage(&mut self, idx: usize, stage: u8) {
+        if let Some(dying) = self.dying.as_mut() {
+            dying[idx] = stage;
+        }
+    }
+
+    /// Allocates the dying stages if the current `Rule` has dying
+    /// states, and drops them otherwise.
+    fn fit_dying(&mut self) {
+        if self.rule.states() > 2 {
+            let size = self.cells.len();
+            self.dying.get_or_insert_with(|| vec![0; size]);
+            self.next_dying.get_or_insert_with(|| vec![0; size]);
+        } else {
+            self.dying = None;
+            self.next_dying = None;
+        }
+    }
+
+    /// Returns a `Keyframe` of the current generation.
+    fn keyframe(&self, edited: bool) -> super::timeline::Keyframe {
+        super::timeline::Keyframe {
+            generation: self.generation,
+            cells: self.cells.clone(),
+            dying: self.dying.clone(),
+            rule: self.rule.clone(),
+            topology: self.topology,
+            edited,
+        }
+    }
+
+    /// Takes a `Keyframe` after an edit, which
+    /// forgets the timeline after the edit.
+    fn record_edit_keyframe(&mut self) {
+        if self.timeline.budget() > 0 {
+            let keyframe = self.keyframe(true);
+            self.timeline.record_edit(keyframe);
+        }
+    }
+
+    /// Applies a `Step` of the undo history, either
+    /// `forwards` to redo it or backwards to undo it.
+    ///
+    /// The cells that change start their age over, as we don't
+    /// keep the ages in the undo history.
+    fn apply_step(&mut self, step: &super::undo::Step, forwards: bool) {
+        // The rule comes first, so that there are dying stages
+        // to restore if it has dying states:
+        if let Some((before, after)) = &step.rules {
+            self.rule = if forwards { after } else { before }.clone();
+            self.fit_dying();
+        }
+        if let Some((before, after)) = step.topologies {
+            self.topology = if forwards { after } else { before };
+        }
+        for change in step.changes.iter() {
+            let (cell, dying) = if forwards {
+                change.after
+            } else {
+                change.before
+            };
+            self.cells[change.index] = cell;
+            self.set_dying_stage(change.index, dying);
+            self.reset_age(change.index);
+        }
+        self.generation = if forwards {
+            step.generations.1
+        } else {
+            step.generations.0
+        };
+        self.population = self.live_cell_count();
+        self.restart_history();
+        // Undoing a tick returns to a generation that's still on the
+        // timeline, while undoing an edit changes the course of the run:
+        if step.generations.0 == step.generations.1 {
+            self.record_edit_keyframe();
+        }
+    }
+
+    /// Switches to another `Rule`.
+    ///
+    /// Dying cells in a stage that the new `Rule` doesn't have
+    /// are dead right away. The dying stages themselves are fitted
+    /// to the new `Rule` by `finish_edit`.
+    fn adopt_rule(&mut self, rule: super::rule::Rule) {
+        let last_dying = rule.states() - 2;
+        if let Some(dying) = self.dying.as_mut() {
+            for stage in dying.iter_mut().filter(|stage| **stage > last_dying) {
+                *stage = 0;
+            }
+        }
+        self.rule = rule;
+    }
+
+    /// Starts counting the age of the `Cell` at the given
+    /// index over, if age tracking is turned on.
+    fn reset_age(&mut self, idx: usize) {
+        if let Some(ages) = self.ages.as_mut() {
+            ages[idx] = 0;
+        }
+    }
+
+    /// Toggles the `Cell` at the given index and keeps
+    /// the `population` up to date.
+    fn toggle_index(&mut self, idx: usize) {
+        // A dying cell is dead, so toggling brings it back to life:
+        self.set_dying_stage(idx, 0);
+        self.reset_age(idx);
+        self.cells[idx].toggle();
+        match self.cells[idx] {
+            super::cell::Cell::Alive => self.population += 1,
+            super::cell::Cell::Dead => self.population -= 1,
+        }
+    }
+
+    /// Returns `true` if the neighbours can be counted by the fast
+    /// path of `torus_neighbor_count`, which only handles the 8 cell
+    /// neighbourhood on a `Topology::Torus`.
+    fn has_fast_path(&self) -> bool {
+        self.topology == super::topology::Topology::Torus && self.rule.is_life_like()
+    }
+
+    /// Counts the living cells at the given `(row, column)` offsets
+    /// from a cell, resolving each of them according to the `Topology`.
+    fn count_neighbors(&self, row: u32, column: u32, offsets: &[(i64, i64)]) -> u32 {
+        offsets
+            .iter()
+            .filter_map(|&(delta_row, delta_column)| {
+                self.resolve(row as i64 + delta_row, column as i64 + delta_column)
+            })
+            .map(|idx| self.cells[idx] as u32)
+            .sum()
+    }
+
+    /// Counts the living neighbours of all cells at once for a Moore
+    /// neighbourhood of the given `range`, indexed like the cells.
+    ///
+    /// # Algorithm explanation
+    ///
+    /// Counting the (2r+1)² cells around every cell one by one gets slow
+    /// for the large ranges of Larger than Life rules. Instead, we build
+    /// a summed-area table, in which each entry holds the number of
+    /// living cells above and left of it. The number of living cells
+    /// within any rectangle then only takes four lookups:
+    ///
+    /// | | | |
+    /// |---|---|---|
+    /// | | left | right |
+    /// | top | a | b |
+    /// | bottom | c | d |
+    ///
+    /// The rectangle contains `d - b - c + a` living cells. The table
+    /// covers the `Universe` plus a border of `range` cells on each
+    /// side, which we fill according to the `Topology`, so that the
+    /// windows at the edges don't need any special casing.
+    fn moore_window_counts(&self, range: u32) -> Vec<u32> {
+        let range = range as usize;
+        let (width, height) = (self.width as usize, self.height as usize);
+        // The table has an extra row and column of zeros at the top and
+        // the left, so that we don't have to check for the first ones:
+        let table_width = width + 2 * range + 1;
+        let table_height = height + 2 * range + 1;
+        let mut table = vec![0u32; table_width * table_height];
+
+        for y in 1..table_height {
+            let mut row_sum = 0;
+            for x in 1..table_width {
+                if let Some(idx) =
+                    self.resolve(y as i64 - 1 - range as i64, x as i64 - 1 - range as i64)
+                {
+                    row_sum += self.cells[idx] as u32;
+                }
+                table[y * table_width + x] = table[(y - 1) * table_width + x] + row_sum;
+            }
+        }
+
+        // The window of the cell at `(row, column)` spans the rows
+        // `row..=row + 2 * range` of the table without its zero border:
+        let side = 2 * range + 1;
+        let mut counts = Vec::with_capacity(width * height);
+        for row in 0..height {
+            for column in 0..width {
+                let (top, left) = (row * table_width, column);
+                let (bottom, right) = ((row + side) * table_width, column + side);
+                let window = table[bottom + right] + table[top + left]
+                    - table[top + right]
+                    - table[bottom + left];
+                // The cell itself isn't one of its neighbours:
+                counts.push(window - self.cells[row * width + column] as u32);
+            }
+        }
+        counts
+    }
+
     /// Counts the living neighbours of a cell.
     ///
     /// We provide the `row` and `column` of the `Cell`
@@ -118,13 +778,33 @@ impl Universe {
     /// for which we'd like to get the number of living
     /// neighbors (cells) returned.
     ///
+    /// The 8 cell neighbourhood on the default `Topology::Torus` takes
+    /// the fast path of `torus_neighbor_count`. For all other topologies
+    /// and neighbourhoods, the neighbours are resolved one by one.
+    // The docs of the commented out version above run into these:
+    #[allow(clippy::empty_line_after_doc_comments)]
+    pub fn live_neighbor_count(&self, row: u32, column: u32) -> u32 {
+        if self.has_fast_path() {
+            return self.torus_neighbor_count(row, column);
+        }
+        let offsets = self.rule.neighborhood().offsets(row);
+        self.count_neighbors(row, column, &offsets)
+    }
+
+    /// Counts the living cells among the 8 neighbours of a cell,
+    /// wrapping around the edges like a `Topology::Torus`.
+    ///
+    /// This is the fast path of `live_neighbor_count`, which `tick`
+    /// calls directly, so that `has_fast_path` is only checked once
+    /// per tick. It doesn't check the rule or the topology itself.
+    ///
     /// # Algorithm explanation
     ///
     /// First, we define the north, south, west and east direction relative
     /// to our current cell that we've defined via `row` and `column`.
     /// We then determine all adjacent cells and add up the number of
     /// living cells, stored in `count`.
-    pub fn live_neighbor_count(&self, row: u32, column: u32) -> u8 {
+    fn torus_neighbor_count(&self, row: u32, column: u32) -> u32 {
         let mut count = 0;
 
         // Determine the north, west, east and south cells:
@@ -162,28 +842,28 @@ impl Universe {
         // concrete identifiers for which columns and rows are adjacent to
         // our cell of interest.
         let nw = self.get_index(north, west);
-        count += self.cells[nw] as u8;
+        count += self.cells[nw] as u32;
 
         let n = self.get_index(north, column);
-        count += self.cells[n] as u8;
+        count += self.cells[n] as u32;
 
         let ne = self.get_index(north, east);
-        count += self.cells[ne] as u8;
+        count += self.cells[ne] as u32;
 
         let w = self.get_index(row, west);
-        count += self.cells[w] as u8;
+        count += self.cells[w] as u32;
 
         let e = self.get_index(row, east);
-        count += self.cells[e] as u8;
+        count += self.cells[e] as u32;
 
         let sw = self.get_index(south, west);
-        count += self.cells[sw] as u8;
+        count += self.cells[sw] as u32;
 
         let s = self.get_index(south, column);
-        count += self.cells[s] as u8;
+        count += self.cells[s] as u32;
 
         let se = self.get_index(south, east);
-        count += self.cells[se] as u8;
+        count += self.cells[se] as u32;
 
         count
     }
@@ -197,8 +877,9 @@ impl Universe {
     ///
     /// We achieve an advance in time by one tick by
     /// calculating the new state of each cell in the
-    /// array (`Vec<Cell>`) and overwriting the
-    /// previous state.
+    /// array (`Vec<Cell>`) and writing it into a second
+    /// buffer of the same size. Both buffers are swapped
+    /// afterwards, so that no memory is allocated or freed.
     pub fn tick(&mut self) {
         // Create a new Timer that tracks each tick of the universe.
         // Due to the nature of the tick method, a new timer is created
@@ -207,9 +888,38 @@ impl Universe {
         // is dropped at the end of the tick method, when it goes out of
         // scope.
         // let _timer = utils::Timer::new("Universe::tick");
-        // Clone the current cells into a new vector:
-        let mut next = self.cells.clone();
-        // let _timer = utils::Timer::new("allocate new cells");
+        // Take the buffer for the next generation out of the universe,
+        // so that we can write into it while reading the current cells.
+        // This leaves an empty vector behind and doesn't allocate:
+        let mut next = std::mem::take(&mut self.next);
+        let mut next_dying = std::mem::take(&mut self.next_dying);
+        // Take the ages out as well, so that we can update them
+        // while reading the rest of the universe:
+        let mut ages = self.ages.take();
+        // Count the cells that change their state on the way:
+        let (mut births, mut deaths) = (0, 0);
+        // The conditions of the rule are unpacked into lookup tables
+        // once, instead of searching them for every cell:
+        let transitions = self.rule.transitions();
+        // Large Moore neighbourhoods are counted all at once with a
+        // summed-area table, see `moore_window_counts`:
+        let fast_path = self.has_fast_path();
+        let neighborhood = self.rule.neighborhood();
+        let window_counts = if neighborhood.kind() == super::neighborhood::NeighborhoodKind::Moore
+            && neighborhood.range() > 1
+        {
+            Some(self.moore_window_counts(neighborhood.range()))
+        } else {
+            None
+        };
+        // For all other neighbourhoods outside of the fast path, we look
+        // up the offsets of the neighbours once per tick instead of once
+        // per cell. Only hexagonal ones differ between even and odd rows:
+        let offsets = if fast_path || window_counts.is_some() {
+            [Vec::new(), Vec::new()]
+        } else {
+            [neighborhood.offsets(0), neighborhood.offsets(1)]
+        };
 
         {
             // let _timer = utils::Timer::new("new generation");
@@ -220,7 +930,11 @@ impl Universe {
                     // Get the cell at the specific index:
                     let cell = self.cells[idx];
                     // Count the number of living neighbors:
-                    let live_neighbors = self.live_neighbor_count(row, col);
+                    let live_neighbors = match &window_counts {
+                        Some(counts) => counts[idx],
+                        None if fast_path => self.torus_neighbor_count(row, col),
+                        None => self.count_neighbors(row, col, &offsets[(row % 2) as usize]),
+                    };
 
                     // Log the amount of living cells and initial state to console output:
                     // log!(
@@ -231,36 +945,122 @@ impl Universe {
                     //     live_neighbors
                     // );
 
-                    // Determine the state of the cell in the next tick in time:
-                    let next_cell = match (cell, live_neighbors) {
-                        // Rule 1: Any live cell with fewer than two live neighbours dies, as if caused by underpopulation.
-                        (super::cell::Cell::Alive, x) if x < 2 => super::cell::Cell::Dead,
-                        // Rule 2: Any live cell with two or three live neighbours lives on to the next generation.
-                        (super::cell::Cell::Alive, 2) | (super::cell::Cell::Alive, 3) => {
-                            super::cell::Cell::Alive
-                        }
-                        // Rule 3: Any live cell with more than three live
-                        // neighbours dies, as if by overpopulation.
-                        (super::cell::Cell::Alive, x) if x > 3 => super::cell::Cell::Dead,
-                        // Rule 4: Any dead cell with exactly three live neighbours becomes a live cell, as if by reproduction.
-                        (super::cell::Cell::Dead, 3) => super::cell::Cell::Alive,
-                        // All other cells remain in the same state.
-                        (otherwise, _) => otherwise,
-                    };
+                    // Determine the state of the cell in the next tick in time.
+                    // For Conway's Game of Life (B3/S23) the rule boils down to:
+                    // Rule 1: Any live cell with fewer than two live neighbours dies, as if caused by underpopulation.
+                    // Rule 2: Any live cell with two or three live neighbours lives on to the next generation.
+                    // Rule 3: Any live cell with more than three live
+                    // neighbours dies, as if by overpopulation.
+                    // Rule 4: Any dead cell with exactly three live neighbours becomes a live cell, as if by reproduction.
+                    // Rules with dying states also keep track of the stage
+                    // a cell is in after it stopped living:
+                    let (next_cell, dying) =
+                        transitions.next_state(cell, self.dying_stage(idx), live_neighbors);
 
                     // Log state change of each cell to console output:
                     // log!("It becomes {:?}", next_cell);
 
+                    match (cell, next_cell) {
+                        (super::cell::Cell::Dead, super::cell::Cell::Alive) => births += 1,
+                        (super::cell::Cell::Alive, super::cell::Cell::Dead) => deaths += 1,
+                        _ => {}
+                    }
+
                     // Insert the `next_cell` into the array of
                     // cells at the next tick in time:
                     next[idx] = next_cell;
+                    if let Some(next_dying) = next_dying.as_mut() {
+                        next_dying[idx] = dying;
+                    }
+
+                    // A cell gets older as long as it stays in the same
+                    // state. The age saturates instead of overflowing,
+                    // so that a very old cell doesn't become a young one:
+                    if let Some(ages) = ages.as_mut() {
+                        ages[idx] = if next_cell == cell {
+                            ages[idx].saturating_add(1)
+                        } else {
+                            0
+                        };
+                    }
                 }
             }
         }
-        // Overwrite the current array of cells with
-        // those at the current time plus one tick:
-        // let _timer = utils::Timer::new("free old cells");
-        self.cells = next;
+        // Swap the current array of cells with those at
+        // the current time plus one tick. The previous cells
+        // become the buffer for the next call to `tick`:
+        self.next = std::mem::replace(&mut self.cells, next);
+        self.next_dying = std::mem::replace(&mut self.dying, next_dying);
+        self.ages = ages;
+
+        self.generation += 1;
+        self.population = self.population + births - deaths;
+        self.births = births;
+        self.deaths = deaths;
+        self.record_state();
+
+        // The previous cells are still around in the buffers for the
+        // next generation, so we can record the tick as a diff:
+        if self.undo.budget() > 0 {
+            self.undo.record(super::undo::Step {
+                changes: self.changes(&self.next, self.next_dying.as_deref()),
+                generations: (self.generation - 1, self.generation),
+                rules: None,
+                topologies: None,
+            });
+        }
+        if self.timeline.wants(self.generation) {
+            let keyframe = self.keyframe(false);
+            self.timeline.record(keyframe);
+        } else {
+            self.timeline.reach(self.generation);
+        }
+    }
+
+    /// Advances the time by `n` ticks.
+    ///
+    /// Calling this once instead of calling `tick` `n` times from
+    /// JavaScript saves us the cost of crossing the boundary between
+    /// JavaScript and WASM for every single generation.
+    pub fn tick_n(&mut self, n: u32) {
+        for _ in 0..n {
+            self.tick();
+        }
+    }
+
+    /// Advances the time until the `condition` is met, but by no more
+    /// than `max_generations` ticks.
+    ///
+    /// The `threshold` is the number of living cells for
+    /// `StopCondition::PopulationExceeds` and is ignored otherwise.
+    /// Returns the number of generations that were actually run, which
+    /// is 0 if the condition was already met before the first tick.
+    pub fn run_until(
+        &mut self,
+        max_generations: u32,
+        condition: StopCondition,
+        threshold: u32,
+    ) -> u32 {
+        // The hashes of all states we've seen during this run:
+        let mut seen = HashSet::new();
+
+        let mut is_met = |universe: &Universe| match condition {
+            StopCondition::Extinct => universe.population == 0,
+            // `insert` returns `false` if the hash was already in the set:
+            StopCondition::Repeats => !seen.insert(universe.state_hash()),
+            StopCondition::PopulationExceeds => universe.population > threshold,
+        };
+
+        if is_met(self) {
+            return 0;
+        }
+        for generation in 1..=max_generations {
+            self.tick();
+            if is_met(self) {
+                return generation;
+            }
+        }
+        max_generations
     }
 
     /// Creates and returns an instance of `Universe`.
@@ -270,6 +1070,8 @@ impl Universe {
     /// allows for different starting universes. This state
     /// can be `TwoSeven`, where the index of each living starting
     /// cell was either divisible by 2 or 7, `Dead` or `Random`.
+    // The helpers below keep their original shape:
+    #[allow(unused_variables, clippy::redundant_locals, clippy::let_and_return)]
     pub fn new(universe_option: UniverseOption, width: u32, height: u32) -> Universe {
         // Enable logging for when our code panics.
         // This is achieved by invoking the set_panic_hook()
@@ -348,12 +1150,155 @@ impl Universe {
         };
 
         // Return the universe:
-        Universe {
+        let mut universe = Universe {
             width,
             height,
+            next: cells.clone(),
+            dying: None,
+            next_dying: None,
+            ages: None,
             cells,
             universe_option,
+            rule: super::rule::Rule::default(),
+            generation: 0,
+            population: 0,
+            births: 0,
+            deaths: 0,
+            states: super::cycle::StateHistory::new(super::cycle::DEFAULT_CAPACITY),
+            topology: super::topology::Topology::Torus,
+            clipboard: None,
+            undo: super::undo::UndoHistory::new(super::undo::DEFAULT_BUDGET),
+            timeline: super::timeline::Timeline::new(
+                super::timeline::DEFAULT_INTERVAL,
+                super::timeline::DEFAULT_BUDGET,
+            ),
+        };
+        universe.population = universe.live_cell_count();
+        universe.restart_history();
+        universe.record_edit_keyframe();
+        universe
+    }
+
+    /// Creates an `Universe` of random cells that can be recreated
+    /// from its `seed`.
+    ///
+    /// Each cell is alive with a probability of `density`, so a
+    /// `density` of 0.5 gives the same soup as `UniverseOption::Random`
+    /// on average. Unlike `UniverseOption::Random`, the same `seed`,
+    /// `density` and size always give the same cells on every platform.
+    pub fn new_random(seed: u64, density: f64, width: u32, height: u32) -> Universe {
+        let mut universe = Universe::new(UniverseOption::Dead, width, height);
+        universe.universe_option = UniverseOption::Random;
+
+        let mut rng = super::rng::SplitMix64::new(seed);
+        for cell in universe.cells.iter_mut() {
+            if rng.chance(density) {
+                *cell = super::cell::Cell::Alive;
+            }
         }
+
+        universe.population = universe.live_cell_count();
+        universe.restart_history();
+        universe.record_edit_keyframe();
+        universe
+    }
+
+    /// Creates an otherwise dead `Universe` with a random soup
+    /// of the given `Symmetry` in its center.
+    ///
+    /// The soup is generated from the `seed`, so a search can be re-run
+    /// by creating the same soups again. See `soup::generate`.
+    ///
+    /// # Errors
+    /// An `Universe` that is smaller than the soup is rejected
+    /// with `PatternError::TooLarge`.
+    pub fn new_soup(
+        symmetry: super::soup::Symmetry,
+        seed: u64,
+        width: u32,
+        height: u32,
+    ) -> Result<Universe, super::pattern::PatternError> {
+        let soup = super::soup::generate(symmetry, seed);
+        let mut universe = Universe::new(UniverseOption::Dead, width, height);
+        universe.check_fits(soup.width(), soup.height())?;
+        // The soup is where the universe starts, not an edit that
+        // could be undone, so it's written without recording it:
+        universe.write_pattern(
+            &soup,
+            (height.saturating_sub(soup.height()) / 2) as i64,
+            (width.saturating_sub(soup.width()) / 2) as i64,
+            super::pattern::MergeMode::Overwrite,
+        );
+
+        universe.restart_history();
+        universe.record_edit_keyframe();
+        Ok(universe)
+    }
+
+    /// Sets the `Rule` by which the `Universe` evolves.
+    ///
+    /// The `rule` is a rulestring in the B/S notation, e.g.
+    /// `B36/S23` for HighLife or `B2/S` for Seeds, optionally with an
+    /// `H` or `V` suffix for the hexagonal or von Neumann neighbourhood.
+    /// Larger neighbourhoods use the HROT notation, e.g.
+    /// `R2,C2,S6-9,B7-8,NM`, or the Larger than Life notation, e.g.
+    /// `R5,C0,M1,S34..58,B34..45,NM`. Generations rules with dying
+    /// states add the number of states, e.g. `B2/S/C3`. If the rulestring
+    /// can't be parsed, the current rule is kept and the error is
+    /// thrown on the JavaScript side. The same goes for hexagonal rules
+    /// on an odd number of rows that wrap around, see `check_rule`.
+    pub fn set_rule(&mut self, rule: &str) -> Result<(), super::rule::RuleError> {
+        let before = self.begin_edit();
+        let rule = rule.parse()?;
+        Universe::check_rule(&rule, self.topology, self.height)?;
+        self.adopt_rule(rule);
+        // The same cells evolve differently under another rule:
+        self.finish_edit(before);
+        Ok(())
+    }
+
+    /// Returns the current `Rule` of the `Universe`
+    /// as a rulestring in the B/S or HROT notation.
+    pub fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /// Sets the neighbourhood in which the living neighbours of a cell
+    /// are counted, while keeping the birth and survival conditions.
+    ///
+    /// # Errors
+    /// A `range` of 0 or above `MAX_RANGE` is rejected with
+    /// `RuleError::InvalidRange`, as are neighbourhoods that are too
+    /// small for the counts of the current rule with
+    /// `RuleError::CountOutOfRange`. Hexagonal neighbourhoods on an odd
+    /// number of rows that wrap around are rejected with
+    /// `RuleError::Unsupported`, see `check_rule`.
+    pub fn set_neighborhood(
+        &mut self,
+        kind: super::neighborhood::NeighborhoodKind,
+        range: u32,
+    ) -> Result<(), super::rule::RuleError> {
+        let before = self.begin_edit();
+        if range == 0 || range > super::neighborhood::MAX_RANGE {
+            return Err(super::rule::RuleError::InvalidRange(range));
+        }
+        let mut rule = self.rule.clone();
+        rule.set_neighborhood(super::neighborhood::Neighborhood::new(kind, range))?;
+        Universe::check_rule(&rule, self.topology, self.height)?;
+        self.rule = rule;
+        // The same cells evolve differently in another neighbourhood:
+        self.finish_edit(before);
+        Ok(())
+    }
+
+    /// Returns the `NeighborhoodKind` of the current `Rule`.
+    pub fn neighborhood_kind(&self) -> super::neighborhood::NeighborhoodKind {
+        self.rule.neighborhood().kind()
+    }
+
+    /// Returns the range of the neighbourhood of the current `Rule`.
+    pub fn neighborhood_range(&self) -> u32 {
+        self.rule.neighborhood().range()
     }
 
     /// Returns the `Universe` as a `String`.
@@ -366,39 +1311,677 @@ impl Universe {
 
     /// Toggles the state of a cell.
     pub fn toggle_cell(&mut self, row: u32, column: u32) {
+        let before = self.begin_edit();
         let idx = self.get_index(row, column);
-        self.cells[idx].toggle();
+        self.toggle_index(idx);
+        self.finish_edit(before);
+    }
+
+    /// Inserts a pattern in the RLE format into the universe.
+    ///
+    /// The top left corner of the pattern is placed at the given
+    /// `row` and `column` and the pattern wraps around the edges.
+    /// Malformed patterns and patterns that don't fit into the
+    /// universe are thrown as errors on the JavaScript side.
+    pub fn insert_rle(
+        &mut self,
+        row: u32,
+        column: u32,
+        rle: &str,
+    ) -> Result<(), super::pattern::PatternError> {
+        let (pattern, states) = super::rle::parse_states_within(rle, self.width, self.height)?;
+        self.insert_states(&pattern, Some(&states), row, column)
+    }
+
+    /// Returns the living cells of the universe in the RLE format.
+    ///
+    /// The pattern is cropped to the bounding box of the living cells
+    /// and the header contains the current rule, so that the result
+    /// can be opened by other Life tools like Golly.
+    ///
+    /// Under rules with dying states, the dying cells are part of the
+    /// pattern as well, which is written in the multi-state RLE format
+    /// of Golly, see `rle::write_states`.
+    pub fn to_rle(&self) -> String {
+        if self.dying.is_none() {
+            return super::rle::write(&self.to_pattern());
+        }
+
+        let (row, column, width, height) = self
+            .bounding_box_of(|idx| {
+                self.cells[idx] == super::cell::Cell::Alive || self.dying_stage(idx) > 0
+            })
+            .unwrap_or((0, 0, 0, 0));
+        // The living state is 1 and the dying stages follow it:
+        let mut states = Vec::with_capacity((width * height) as usize);
+        for region_row in row..row + height {
+            for region_column in column..column + width {
+                let idx = self.get_index(region_row, region_column);
+                states.push(match self.cells[idx] {
+                    super::cell::Cell::Alive => 1,
+                    super::cell::Cell::Dead => match self.dying_stage(idx) {
+                        0 => 0,
+                        stage => stage + 1,
+                    },
+                });
+            }
+        }
+        super::rle::write_states(&self.region(row, column, width, height), &states)
+    }
+
+    /// Inserts a pattern in the plaintext (`.cells`) format
+    /// into the universe.
+    ///
+    /// Just like `insert_rle`, the top left corner of the pattern is
+    /// placed at the given `row` and `column` and the pattern wraps
+    /// around the edges.
+    pub fn insert_plaintext(
+        &mut self,
+        row: u32,
+        column: u32,
+        plaintext: &str,
+    ) -> Result<(), super::pattern::PatternError> {
+        let pattern = super::plaintext::parse_within(plaintext, self.width, self.height)?;
+        self.insert_pattern(&pattern, row, column)
+    }
+
+    /// Returns the living cells of the universe in
+    /// the plaintext (`.cells`) format.
+    pub fn to_plaintext(&self) -> String {
+        super::plaintext::write(&self.to_pattern())
     }
 
-    /// Inserts a glider pattern into the universe.
+    /// Inserts a pattern in the Life 1.06 format into the universe.
+    ///
+    /// The topmost and leftmost living cells of the pattern end up
+    /// in the given `row` and `column`.
+    pub fn insert_life106(
+        &mut self,
+        row: u32,
+        column: u32,
+        life106: &str,
+    ) -> Result<(), super::pattern::PatternError> {
+        let pattern = super::life106::parse_within(life106, self.width, self.height)?;
+        self.insert_pattern(&pattern, row, column)
+    }
+
+    /// Returns the living cells of the universe in the Life 1.06
+    /// format, relative to the top left corner of their bounding box.
+    pub fn to_life106(&self) -> String {
+        super::life106::write(&self.to_pattern())
+    }
+
+    /// Inserts a built-in pattern from the `library` in the given
+    /// `Orientation`, with its top left corner at `row` and `column`.
+    ///
+    /// Just like `insert_rle`, the pattern overwrites the cells below
+    /// it and wraps around the edges of the universe. Unknown names
+    /// are thrown as errors on the JavaScript side.
+    pub fn place_pattern(
+        &mut self,
+        name: &str,
+        row: u32,
+        column: u32,
+        orientation: super::pattern::Orientation,
+    ) -> Result<(), super::pattern::PatternError> {
+        let pattern = super::library::get(name)?.oriented(orientation);
+        self.insert_pattern(&pattern, row, column)
+    }
+
+    /// Rotates or mirrors a rectangular region of the universe.
+    ///
+    /// The region starts with its top left corner at `row` and `column`
+    /// and continues beyond the edges according to the `Topology`. The
+    /// transformed cells keep the same top left corner, so regions that
+    /// are rotated by 90° or 270° or mirrored along a diagonal swap
+    /// their `width` and `height`. Cells of the region that aren't
+    /// covered by the transformed one anymore are cleared.
+    ///
+    /// # Errors
+    /// Regions that don't fit into the universe, before or after the
+    /// transformation, are rejected with `PatternError::TooLarge`.
+    pub fn transform_region(
+        &mut self,
+        row: u32,
+        column: u32,
+        width: u32,
+        height: u32,
+        orientation: super::pattern::Orientation,
+    ) -> Result<(), super::pattern::PatternError> {
+        let before = self.begin_edit();
+        self.check_fits(width, height)?;
+        let transformed = self
+            .region(row, column, width, height)
+            .oriented(orientation);
+        self.check_fits(transformed.width(), transformed.height())?;
+
+        self.write_region(row, column, width, height, super::cell::Cell::Dead);
+        self.write_pattern(
+            &transformed,
+            row as i64,
+            column as i64,
+            super::pattern::MergeMode::Overwrite,
+        );
+        self.finish_edit(before);
+        Ok(())
+    }
+
+    /// Moves a rectangular region of the universe by `delta_row` rows
+    /// and `delta_column` columns.
+    ///
+    /// The region wraps around glued edges and cells that are moved
+    /// beyond bounded ones are dropped. The cells the region leaves
+    /// behind are cleared.
+    ///
+    /// # Errors
+    /// Regions that don't fit into the universe are rejected
+    /// with `PatternError::TooLarge`.
+    pub fn translate_region(
+        &mut self,
+        row: u32,
+        column: u32,
+        width: u32,
+        height: u32,
+        delta_row: i32,
+        delta_column: i32,
+    ) -> Result<(), super::pattern::PatternError> {
+        let before = self.begin_edit();
+        self.check_fits(width, height)?;
+        let moved = self.region(row, column, width, height);
+
+        self.write_region(row, column, width, height, super::cell::Cell::Dead);
+        self.write_pattern(
+            &moved,
+            row as i64 + delta_row as i64,
+            column as i64 + delta_column as i64,
+            super::pattern::MergeMode::Overwrite,
+        );
+        self.finish_edit(before);
+        Ok(())
+    }
+
+    /// Moves all cells of the universe by `delta_row` rows and
+    /// `delta_column` columns, see `translate_region`.
+    pub fn translate(&mut self, delta_row: i32, delta_column: i32) {
+        // The whole universe always fits into itself:
+        let _ = self.translate_region(0, 0, self.width, self.height, delta_row, delta_column);
+    }
+
+    /// Copies a rectangular region of the universe to the clipboard,
+    /// replacing whatever was copied before.
+    ///
+    /// The region continues beyond the edges according to the
+    /// `Topology`, just like for `region`.
+    ///
+    /// # Errors
+    /// Regions that don't fit into the universe are rejected
+    /// with `PatternError::TooLarge`.
+    pub fn copy_region(
+        &mut self,
+        row: u32,
+        column: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), super::pattern::PatternError> {
+        self.check_fits(width, height)?;
+        self.clipboard = Some(self.region(row, column, width, height));
+        Ok(())
+    }
+
+    /// Copies a rectangular region of the universe to the clipboard
+    /// and clears it afterwards, see `copy_region`.
+    pub fn cut_region(
+        &mut self,
+        row: u32,
+        column: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), super::pattern::PatternError> {
+        self.copy_region(row, column, width, height)?;
+        self.clear_region(row, column, width, height)
+    }
+
+    /// Pastes the clipboard with its top left corner at the given
+    /// `row` and `column`.
+    ///
+    /// The `MergeMode` decides how the pasted cells are combined with
+    /// the cells that are already there. The clipboard stays as it is,
+    /// so it can be pasted as often as needed.
+    ///
+    /// # Errors
+    /// Nothing can be pasted before a region was copied or cut,
+    /// which is rejected with `PatternError::EmptyClipboard`.
+    pub fn paste(
+        &mut self,
+        row: u32,
+        column: u32,
+        mode: super::pattern::MergeMode,
+    ) -> Result<(), super::pattern::PatternError> {
+        let before = self.begin_edit();
+        let clipboard = self
+            .clipboard
+            .take()
+            .ok_or(super::pattern::PatternError::EmptyClipboard)?;
+        self.write_pattern(&clipboard, row as i64, column as i64, mode);
+        self.clipboard = Some(clipboard);
+        self.finish_edit(before);
+        Ok(())
+    }
+
+    /// Returns whether there's a region on the clipboard to paste.
+    pub fn has_clipboard(&self) -> bool {
+        self.clipboard.is_some()
+    }
+
+    /// Returns the width of the region on the clipboard,
+    /// or 0 if the clipboard is empty.
+    pub fn clipboard_width(&self) -> u32 {
+        self.clipboard.as_ref().map_or(0, |pattern| pattern.width())
+    }
+
+    /// Returns the height of the region on the clipboard,
+    /// or 0 if the clipboard is empty.
+    pub fn clipboard_height(&self) -> u32 {
+        self.clipboard
+            .as_ref()
+            .map_or(0, |pattern| pattern.height())
+    }
+
+    /// Kills all cells of a rectangular region.
+    ///
+    /// # Errors
+    /// Regions that don't fit into the universe are rejected
+    /// with `PatternError::TooLarge`.
+    pub fn clear_region(
+        &mut self,
+        row: u32,
+        column: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), super::pattern::PatternError> {
+        let before = self.begin_edit();
+        self.check_fits(width, height)?;
+        self.write_region(row, column, width, height, super::cell::Cell::Dead);
+        self.finish_edit(before);
+        Ok(())
+    }
+
+    /// Brings all cells of a rectangular region to life.
+    ///
+    /// # Errors
+    /// Regions that don't fit into the universe are rejected
+    /// with `PatternError::TooLarge`.
+    pub fn fill_region(
+        &mut self,
+        row: u32,
+        column: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), super::pattern::PatternError> {
+        let before = self.begin_edit();
+        self.check_fits(width, height)?;
+        self.write_region(row, column, width, height, super::cell::Cell::Alive);
+        self.finish_edit(before);
+        Ok(())
+    }
+
+    /// Toggles the cells of a glider around the given cell.
     pub fn toggle_glider(&mut self, row: u32, column: u32) {
+        let before = self.begin_edit();
         // Glider pattern, where x marks the clicked cell
         // and the other cells to be toggled are marked with a o:
         //     o
         // o x o
         //   o o
-        // Grab the width and height of the universe as a tuple:
-        let (width, height) = (self.width, self.height);
+        // The glider of the library is placed with its center on the
+        // clicked cell. Cells beyond the edges are resolved according
+        // to the topology of the universe and dropped if they're
+        // outside of it:
+        let glider = super::library::get("glider").expect("the glider is a built-in pattern");
+        let cells_to_toggle: Vec<usize> = glider
+            .live_cells()
+            .filter_map(|(glider_row, glider_column)| {
+                self.resolve(
+                    row as i64 + glider_row as i64 - 1,
+                    column as i64 + glider_column as i64 - 1,
+                )
+            })
+            .collect();
 
-        // If the clicked cell is on the very right, left, top or bottom of
-        // the universe, we can't insert the glider pattern, so return:
-        if width - 1 == column || column == 0 || height - 1 == row || row == 0 {
-            return;
+        for cell in cells_to_toggle.iter() {
+            self.toggle_index(*cell);
+        }
+        self.finish_edit(before);
+    }
+
+    /// Changes the size of the universe and keeps its cells.
+    ///
+    /// The cells stay where they are relative to the `anchor`, so a
+    /// universe that's resized around its `Anchor::Center` grows or
+    /// shrinks evenly on all sides. New cells are dead and cells beyond
+    /// the new edges are cropped. Steps that can be undone and the
+    /// timeline refer to the old size, so they're forgotten.
+    ///
+    /// # Errors
+    /// Sizes with more than `pattern::MAX_CELLS` cells are rejected with
+    /// `PatternError::TooLarge`, just like for a `Pattern`. A hexagonal
+    /// `Rule` can't run on an odd number of rows that wrap around, see
+    /// `check_rule`. Such a `height` is rejected with
+    /// `PatternError::InvalidRule`. The universe keeps its size in both
+    /// cases.
+    pub fn resize(
+        &mut self,
+        width: u32,
+        height: u32,
+        anchor: Anchor,
+    ) -> Result<(), super::pattern::PatternError> {
+        let size = (width as usize)
+            .checked_mul(height as usize)
+            .filter(|&size| size as u64 <= super::pattern::MAX_CELLS)
+            .ok_or(super::pattern::PatternError::TooLarge { width, height })?;
+        Universe::check_rule(&self.rule, self.topology, height)
+            .map_err(super::pattern::PatternError::InvalidRule)?;
+        // The offset of the old cells within the resized universe:
+        let offset = |old: u32, new: u32, start: bool, end: bool| -> i64 {
+            let difference = new as i64 - old as i64;
+            match (start, end) {
+                (true, _) => 0,
+                (_, true) => difference,
+                _ => difference.div_euclid(2),
+            }
         };
+        let (top, bottom) = (
+            matches!(anchor, Anchor::TopLeft | Anchor::TopRight),
+            matches!(anchor, Anchor::BottomLeft | Anchor::BottomRight),
+        );
+        let (left, right) = (
+            matches!(anchor, Anchor::TopLeft | Anchor::BottomLeft),
+            matches!(anchor, Anchor::TopRight | Anchor::BottomRight),
+        );
+        let row_offset = offset(self.height, height, top, bottom);
+        let column_offset = offset(self.width, width, left, right);
+
+        let mut cells = vec![super::cell::Cell::Dead; size];
+        let mut dying = self.dying.as_ref().map(|_| vec![0; size]);
+        let mut ages = self.ages.as_ref().map(|_| vec![0; size]);
+        for row in 0..height {
+            for column in 0..width {
+                let (old_row, old_column) =
+                    (row as i64 - row_offset, column as i64 - column_offset);
+                if old_row < 0
+                    || old_column < 0
+                    || old_row >= self.height as i64
+                    || old_column >= self.width as i64
+                {
+                    continue;
+                }
+                let old_idx = self.get_index(old_row as u32, old_column as u32);
+                let idx = (row * width + column) as usize;
+                cells[idx] = self.cells[old_idx];
+                if let Some(dying) = dying.as_mut() {
+                    dying[idx] = self.dying_stage(old_idx);
+                }
+                if let (Some(ages), Some(old_ages)) = (ages.as_mut(), self.ages.as_ref()) {
+                    ages[idx] = old_ages[old_idx];
+                }
+            }
+        }
 
-        let left = self.get_index(row, column - 1);
-        let right = self.get_index(row, column + 1);
-        let bottom = self.get_index(row + 1, column);
-        let bottom_right = self.get_index(row + 1, column + 1);
-        let top_right = self.get_index(row - 1, column + 1);
+        self.width = width;
+        self.height = height;
+        self.next = cells.clone();
+        self.next_dying = dying.clone();
+        self.cells = cells;
+        self.dying = dying;
+        self.ages = ages;
+        self.population = self.live_cell_count();
 
-        let cells_to_toggle = [left, right, bottom, bottom_right, top_right];
+        self.undo.clear();
+        self.timeline.clear();
+        self.restart_history();
+        self.record_edit_keyframe();
+        Ok(())
+    }
 
-        for cell in cells_to_toggle.iter() {
-            self.cells[*cell].toggle();
+    /// Sets the `Topology`, which determines what lies
+    /// beyond the edges of the universe.
+    ///
+    /// # Errors
+    /// A hexagonal `Rule` can't run on an odd number of rows that wrap
+    /// around, see `check_rule`. Such a `topology` is rejected with
+    /// `RuleError::Unsupported` and the current one is kept.
+    pub fn set_topology(
+        &mut self,
+        topology: super::topology::Topology,
+    ) -> Result<(), super::rule::RuleError> {
+        Universe::check_rule(&self.rule, topology, self.height)?;
+        let before = self.begin_edit();
+        self.topology = topology;
+        // The same cells evolve differently on another topology,
+        // so this is an edit of its own:
+        self.finish_edit(before);
+        Ok(())
+    }
+
+    /// Returns the current `Topology` of the universe.
+    pub fn topology(&self) -> super::topology::Topology {
+        self.topology
+    }
+
+    /// Returns the number of ticks since the
+    /// `Universe` was created.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Returns the number of living cells.
+    pub fn population(&self) -> u32 {
+        self.population
+    }
+
+    /// Returns the number of cells that were
+    /// born during the last tick.
+    pub fn births(&self) -> u32 {
+        self.births
+    }
+
+    /// Returns the number of cells that died
+    /// during the last tick.
+    pub fn deaths(&self) -> u32 {
+        self.deaths
+    }
+
+    /// Returns a snapshot of all statistics at once.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            generation: self.generation,
+            population: self.population,
+            births: self.births,
+            deaths: self.deaths,
+        }
+    }
+
+    /// Takes a census of the objects in the universe, which
+    /// should have settled before. See `census::take`.
+    pub fn census(&self) -> super::census::Census {
+        super::census::take(self, &self.rule)
+    }
+
+    /// Returns the `Oscillation` the universe settled into, if
+    /// it repeated a state within the remembered generations.
+    ///
+    /// A universe without living cells also repeats itself, so
+    /// check `stability()` to tell it apart from a still life.
+    pub fn oscillation(&self) -> Option<super::cycle::Oscillation> {
+        self.states.oscillation()
+    }
+
+    /// Returns whether the universe has died out, stopped changing,
+    /// entered an oscillation or is still evolving.
+    pub fn stability(&self) -> super::cycle::Stability {
+        match self.states.oscillation() {
+            _ if self.population == 0 => super::cycle::Stability::Extinct,
+            Some(oscillation) if oscillation.period == 1 => super::cycle::Stability::Still,
+            Some(_) => super::cycle::Stability::Oscillating,
+            None => super::cycle::Stability::Evolving,
+        }
+    }
+
+    /// Sets the number of past states that are remembered to detect
+    /// an oscillation, which is also its longest detectable period.
+    /// A `capacity` of 0 turns the detection off.
+    pub fn set_history_capacity(&mut self, capacity: u32) {
+        self.states.set_capacity(capacity);
+        self.restart_history();
+    }
+
+    /// Reverts the last edit or tick.
+    ///
+    /// Edits are cell toggles, placed patterns, changes to regions and
+    /// changes to the rule. Returns `false` if there's nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.undo.pop_undo() {
+            Some(step) => {
+                self.apply_step(&step, false);
+                self.undo.push_redo(step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Repeats the last edit or tick that was undone.
+    ///
+    /// Any new edit or tick forgets the undone ones.
+    /// Returns `false` if there's nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.undo.pop_redo() {
+            Some(step) => {
+                self.apply_step(&step, true);
+                self.undo.push_undo(step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns `true` if there's an edit or tick to undo.
+    pub fn can_undo(&self) -> bool {
+        self.undo.can_undo()
+    }
+
+    /// Returns `true` if there's an edit or tick to redo.
+    pub fn can_redo(&self) -> bool {
+        self.undo.can_redo()
+    }
+
+    /// Sets the number of bytes the undo history may use. The oldest
+    /// edits and ticks are forgotten first when it runs out of space.
+    /// A `budget` of 0, which is the default, turns the undo history
+    /// off, so that ticks don't have to compare all cells.
+    pub fn set_undo_budget(&mut self, budget: u32) {
+        self.undo.set_budget(budget as usize);
+    }
+
+    /// Returns the number of bytes the undo history may use.
+    pub fn undo_budget(&self) -> u32 {
+        self.undo.budget() as u32
+    }
+
+    /// Returns to an earlier generation or forwards to a later one that
+    /// was visited before, without losing the generations in between.
+    ///
+    /// The last keyframe before the `generation` is restored and ticked
+    /// forward, so seeking takes at most as many ticks as there are
+    /// generations between two keyframes. The whole jump can be undone
+    /// like a single tick. The ages of the cells start over.
+    ///
+    /// # Errors
+    /// Generations before the first keyframe, after the last visited
+    /// one or before the last edit can't be recomputed and are
+    /// rejected with `TimelineError::NotVisited`.
+    pub fn seek(&mut self, generation: u32) -> Result<(), super::timeline::TimelineError> {
+        if generation == self.generation {
+            return Ok(());
+        }
+        let keyframe = self
+            .timeline
+            .keyframe_before(generation)
+            .ok_or(super::timeline::TimelineError::NotVisited { generation })?
+            .clone();
+
+        let (cells, dying) = (self.cells.clone(), self.dying.clone());
+        let (rule, topology) = (self.rule.clone(), self.topology);
+        let previous_generation = self.generation;
+
+        self.cells = keyframe.cells;
+        self.dying = keyframe.dying;
+        self.rule = keyframe.rule;
+        self.fit_dying();
+        self.topology = keyframe.topology;
+        self.generation = keyframe.generation;
+        self.population = self.live_cell_count();
+        if let Some(ages) = self.ages.as_mut() {
+            ages.iter_mut().for_each(|age| *age = 0);
+        }
+        self.restart_history();
+
+        // The ticks in between aren't steps of their own, so we
+        // leave the undo history out while ticking forward:
+        let mut undo = std::mem::replace(&mut self.undo, super::undo::UndoHistory::new(0));
+        while self.generation < generation {
+            self.tick();
+        }
+
+        let rules = if rule != self.rule {
+            Some((rule, self.rule.clone()))
+        } else {
+            None
+        };
+        // A keyframe from before a change of the topology brings
+        // back the old one, which undoing the seek has to restore:
+        let topologies = if topology != self.topology {
+            Some((topology, self.topology))
+        } else {
+            None
+        };
+        undo.record(super::undo::Step {
+            changes: self.changes(&cells, dying.as_deref()),
+            generations: (previous_generation, self.generation),
+            rules,
+            topologies,
+        });
+        self.undo = undo;
+        Ok(())
+    }
+
+    /// Returns the first generation `seek` can return to.
+    pub fn earliest_generation(&self) -> u32 {
+        self.timeline.earliest().unwrap_or(self.generation)
+    }
+
+    /// Returns the last generation `seek` can go forward to.
+    pub fn latest_generation(&self) -> u32 {
+        self.timeline.latest().unwrap_or(self.generation)
+    }
+
+    /// Sets the number of bytes the keyframes of the timeline may use.
+    /// When it runs out of space, the keyframes are thinned out first
+    /// and then the oldest ones are forgotten. A `budget` of 0, which
+    /// is the default, turns the timeline off. Turning it on starts the
+    /// timeline at the current generation.
+    pub fn set_timeline_budget(&mut self, budget: u32) {
+        self.timeline.set_budget(budget as usize);
+        if self.timeline.earliest().is_none() {
+            self.record_edit_keyframe();
         }
     }
 
+    /// Returns the number of bytes the keyframes of the timeline may use.
+    pub fn timeline_budget(&self) -> u32 {
+        self.timeline.budget() as u32
+    }
+
     // A couple more getter functions for our Universe,
     // which will be exposed to the JavaScript API.
     /// Returns the `width` of the `Universe`.
@@ -416,6 +1999,64 @@ impl Universe {
     pub fn cells(&self) -> *const super::cell::Cell {
         self.cells.as_ptr()
     }
+
+    /// Returns the number of states of the current `Rule`, including
+    /// the living and the dead one. Rules without dying states have 2.
+    pub fn state_count(&self) -> u32 {
+        self.rule.states() as u32
+    }
+
+    /// Turns the tracking of the age of each cell on or off.
+    ///
+    /// The age of a cell is the number of ticks it has been alive
+    /// or dead in a row, up to 65535. All cells start at an age of 0
+    /// when the tracking is turned on. Turning it off frees the
+    /// memory of the ages.
+    pub fn set_age_tracking(&mut self, enabled: bool) {
+        self.ages = if enabled {
+            Some(vec![0; self.cells.len()])
+        } else {
+            None
+        };
+    }
+
+    /// Returns `true` if the age of each cell is tracked.
+    pub fn is_tracking_ages(&self) -> bool {
+        self.ages.is_some()
+    }
+
+    /// Returns a raw pointer to the ages of the cells, with two bytes
+    /// per cell. The pointer is null if age tracking is turned off.
+    pub fn ages(&self) -> *const u16 {
+        self.ages
+            .as_ref()
+            .map_or(std::ptr::null(), |ages| ages.as_ptr())
+    }
+
+    /// Returns a raw pointer to the dying stages of the cells, one
+    /// byte per cell. A dying cell counts up from 1 to
+    /// `state_count() - 2`, living and dead cells are 0. The pointer
+    /// is null for rules without dying states.
+    pub fn dying_cells(&self) -> *const u8 {
+        self.dying
+            .as_ref()
+            .map_or(std::ptr::null(), |dying| dying.as_ptr())
+    }
+}
+
+// Two universes are equal if their cells are in the same state.
+// The buffer for the next generation is only scratch space and
+// holds whatever generation came before, so we leave it out.
+impl PartialEq for Universe {
+    fn eq(&self, other: &Universe) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self.cells == other.cells
+            && self.dying == other.dying
+            && self.universe_option == other.universe_option
+            && self.rule == other.rule
+            && self.topology == other.topology
+    }
 }
 
 // Implementing the Display trait from Rust's standard library for Universe
@@ -423,22 +2064,38 @@ impl Universe {
 // access to the to_string method.
 impl fmt::Display for Universe {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // On a hexagonal grid, every odd row is shifted to the right by
+        // half a cell. We put a space between the cells of each row and
+        // indent the odd rows by one, so that the shift stays visible:
+        let is_hexagonal =
+            self.neighborhood_kind() == super::neighborhood::NeighborhoodKind::Hexagonal;
         // Slice the 2D array into lines based on the
         // width of a chunk which is also the width
         // of the Universe instance:
-        for line in self.cells.as_slice().chunks(self.width as usize) {
+        for (row, line) in self
+            .cells
+            .as_slice()
+            .chunks(self.width as usize)
+            .enumerate()
+        {
+            if is_hexagonal && row % 2 == 1 {
+                write!(f, " ")?;
+            }
             // For each cell in a line we then determine its state and write the
             // correct symbol:
-            for &cell in line {
+            for (column, &cell) in line.iter().enumerate() {
                 let symbol = if cell == super::cell::Cell::Dead {
                     '◻'
                 } else {
                     '◼'
                 };
+                if is_hexagonal && column > 0 {
+                    write!(f, " ")?;
+                }
                 write!(f, "{}", symbol)?;
             }
             // Jump to the next line after the end of the previous one:
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
 
         Ok(())
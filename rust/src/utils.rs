@@ -5,10 +5,13 @@ use web_sys::console;
 ///
 /// Each timer has a `name`, which is used to identify it in the console.
 /// The data is printed to the console when the timer is dropped.
+// The timers in `Universe::tick` are commented out unless we profile.
+#[allow(dead_code)]
 pub struct Timer<'a> {
     name: &'a str,
 }
 
+#[allow(dead_code)]
 impl<'a> Timer<'a> {
     /// Create a new timer with the given `name`.
     ///